@@ -1,5 +1,6 @@
-use calculator::expression::{eval, Expression, Parser, Rule};
-use calculator::types::{MathError, Token};
+use calculator::environment::Environment;
+use calculator::expression::{evaluate, EvalOptions, Evaluation, Mode};
+use calculator::types::{EvalError, MathError};
 use eframe::egui::epaint::{color, Shadow};
 use eframe::egui::{Align2, Frame, Window};
 use eframe::{egui, epi};
@@ -7,7 +8,12 @@ use eframe::{egui, epi};
 pub struct CalcApp {
     input: String,
     prev_input: String,
-    result: Result<Token, MathError>,
+    result: Result<Evaluation, EvalError>,
+    options: EvalOptions,
+    /// Results are written as mixed numbers like 5½ instead of 11/2.
+    mixed: bool,
+    /// Variables and results stored by pressing enter.
+    env: Environment,
 }
 
 impl Default for CalcApp {
@@ -15,7 +21,10 @@ impl Default for CalcApp {
         Self {
             input: "".to_string(),
             prev_input: "".to_string(),
-            result: Err(MathError::None),
+            result: Err(MathError::None.into()),
+            options: EvalOptions::default(),
+            mixed: false,
+            env: Environment::default(),
         }
     }
 }
@@ -26,25 +35,89 @@ impl epi::App for CalcApp {
             .anchor(Align2::CENTER_CENTER, egui::Vec2::default())
             .frame(Frame::window(&ctx.style()))
             .show(ctx, |ui| {
-                ui.text_edit_singleline(&mut self.input);
-                if self.input != self.prev_input {
+                let input = ui.text_edit_singleline(&mut self.input);
+                let submitted = input.lost_focus() && ui.input().key_pressed(egui::Key::Enter);
+                let old_options = self.options;
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.options.mode, Mode::Exact, "Exact");
+                    ui.radio_value(&mut self.options.mode, Mode::Strict, "Exact only");
+                    ui.radio_value(&mut self.options.mode, Mode::Float, "Fast");
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.options.steps, "Show steps");
+                    ui.checkbox(&mut self.mixed, "Mixed fractions");
+                    ui.checkbox(&mut self.options.latex, "LaTeX input");
+                });
+                let options_changed = self.options != old_options;
+                if self.input != self.prev_input || options_changed {
                     self.prev_input = self.input.clone();
                     if self.input == "!" {
                         frame.quit();
                     } else if self.input.is_empty() {
-                        self.result = Err(MathError::None);
+                        self.result = Err(MathError::None.into());
                     } else {
-                        self.result = match Expression::parse(Rule::calculation, &self.input) {
-                            Ok(calc) => eval(calc),
-                            Err(_) => Err(MathError::SyntaxError),
+                        // Assignments, the history and the random numbers only change once enter
+                        // is pressed.
+                        let mut env = self.env.preview();
+                        self.result = evaluate(&self.input, &mut env, &self.options);
+                    }
+                }
+                if submitted && !self.input.is_empty() {
+                    self.result = evaluate(&self.input, &mut self.env, &self.options);
+                }
+                match &self.result {
+                    Err(e) if e.kind == MathError::None => {
+                        ui.label("Awaiting input...");
+                    }
+                    Err(e) => {
+                        ui.label(format!("Got Error: {}", e));
+                        if let Some(highlight) = e.highlight(&self.input) {
+                            ui.monospace(highlight);
+                        }
+                    }
+                    Ok(evaluation) => {
+                        for step in &evaluation.steps {
+                            ui.label(step.to_string());
+                        }
+                        match &evaluation.result {
+                            Some(result) => {
+                                let label = match self.mixed {
+                                    true => ui.label(format!("Got Result: {:#}", result)),
+                                    false => ui.label(format!("Got Result: {}", result)),
+                                };
+                                if let Some(latex) = result.to_latex() {
+                                    if ui.button("Copy as LaTeX").clicked() {
+                                        ui.output().copied_text = latex;
+                                    }
+                                }
+                                label
+                            }
+                            None => {
+                                ui.label("No result, press enter to store the definition or seed")
+                            }
+                        };
+                        for warning in &evaluation.warnings {
+                            ui.colored_label(
+                                color::Color32::YELLOW,
+                                format!("Warning: {}", warning),
+                            );
                         }
                     }
                 }
-                ui.label(match &self.result {
-                    Err(MathError::None) => "Awaiting input...".to_string(),
-                    Err(e) => format!("Got Error: {}", e),
-                    Ok(t) => format!("Got Result: {:?}", t),
-                });
+                for (name, value) in self.env.variables() {
+                    ui.label(format!("{} = {}", name, value));
+                }
+                for (name, function) in self.env.functions() {
+                    ui.label(format!(
+                        "{}({}) = {}",
+                        name,
+                        function.params.join("; "),
+                        function.body
+                    ));
+                }
+                for (number, value) in self.env.history().iter().enumerate().rev() {
+                    ui.label(format!("${} = {}", number + 1, value));
+                }
             });
 
         frame.set_window_size(ctx.used_size());
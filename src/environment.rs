@@ -0,0 +1,153 @@
+use crate::random::Random;
+use crate::registry::Registry;
+use crate::value::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// How deeply user functions may call each other before evaluation is stopped.
+pub const MAX_CALL_DEPTH: usize = 64;
+
+/// A function defined like `f(x) = x^2`, the body is parsed again each time it is called.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub params: Vec<String>,
+    pub body: String,
+}
+
+lazy_static! {
+    static ref BUILTINS: Arc<Registry> = Arc::new(Registry::default());
+}
+
+/// Values that calculations can refer to by name or by their position in the history.
+///
+/// Numbers are stored as exact [`crate::types::Token`]s so that `a = sqrt(2); a^2` is exactly 2.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    variables: BTreeMap<String, Value>,
+    functions: BTreeMap<String, Function>,
+    history: Vec<Value>,
+    /// The number of user function calls being evaluated.
+    depth: usize,
+    /// Shared between environments until one of them registers something.
+    registry: Arc<Registry>,
+    random: Random,
+}
+
+impl Default for Environment {
+    /// An empty environment with the built-in functions.
+    fn default() -> Environment {
+        Environment::with_registry(BUILTINS.clone())
+    }
+}
+
+impl Environment {
+    pub fn with_registry(registry: Arc<Registry>) -> Environment {
+        Environment {
+            variables: BTreeMap::new(),
+            functions: BTreeMap::new(),
+            history: vec![],
+            depth: 0,
+            registry,
+            random: Random::default(),
+        }
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn registry_mut(&mut self) -> &mut Registry {
+        Arc::make_mut(&mut self.registry)
+    }
+
+    pub fn random(&self) -> &Random {
+        &self.random
+    }
+
+    /// A copy to try out a calculation in, such as the preview of the GUI. Its random numbers are
+    /// the ones this environment would draw next, but drawing them or `seed(n)` leaves this
+    /// environment as it was.
+    pub fn preview(&self) -> Environment {
+        Environment {
+            random: self.random.snapshot(),
+            ..self.clone()
+        }
+    }
+
+    /// Makes the random numbers that follow reproducible, like `seed(n)` in a calculation.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random.reseed(seed);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.variables.get(name)
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    /// All variables, sorted by name.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.get(name)
+    }
+
+    pub fn define(&mut self, name: &str, function: Function) {
+        self.functions.insert(name.to_string(), function);
+    }
+
+    /// All user functions, sorted by name.
+    pub fn functions(&self) -> impl Iterator<Item = (&str, &Function)> {
+        self.functions
+            .iter()
+            .map(|(name, function)| (name.as_str(), function))
+    }
+
+    /// The environment to evaluate the body of `function` in, with its parameters set to `args`.
+    ///
+    /// Returns `None` once [`MAX_CALL_DEPTH`] is reached.
+    pub(crate) fn call_scope(&self, function: &Function, args: Vec<Value>) -> Option<Environment> {
+        if self.depth >= MAX_CALL_DEPTH {
+            return None;
+        }
+        let mut scope = self.clone();
+        scope.depth += 1;
+        for (param, arg) in function.params.iter().zip(args) {
+            scope.set(param, arg);
+        }
+        Some(scope)
+    }
+
+    /// The environment to evaluate an expression in where `symbol` has no value, like the `x`
+    /// in `solve(x^2 = 2; x)` even if `x` is a variable.
+    pub(crate) fn symbol_scope(&self, symbol: &str) -> Environment {
+        let mut scope = self.clone();
+        scope.variables.remove(symbol);
+        scope
+    }
+
+    /// Adds a result to the history, it can then be used as `ans` or `$n`.
+    pub fn push_answer(&mut self, value: Value) {
+        self.history.push(value);
+    }
+
+    /// The `number`th result, counting from 1.
+    pub fn answer(&self, number: usize) -> Option<&Value> {
+        self.history.get(number.checked_sub(1)?)
+    }
+
+    pub fn last_answer(&self) -> Option<&Value> {
+        self.history.last()
+    }
+
+    /// Every result so far, the first one is `$1`.
+    pub fn history(&self) -> &[Value] {
+        &self.history
+    }
+}
@@ -1,12 +1,29 @@
+use pest::error::InputLocation;
 use pest::iterators::{Pair, Pairs};
 use pest::prec_climber::*;
 pub use pest::Parser;
 
+use crate::environment::{Environment, Function};
+use crate::float::{eval_float, float_function};
+use crate::integrate::integrate;
+use crate::latex::translate;
+use crate::linsolve::linsolve;
+use crate::list::range;
+use crate::matrix::Matrix;
 use crate::my_math::ten_to_the_power_of;
-use crate::number::{add, div, exp, mul, sub};
-use crate::trig_number::{acos, asin, atan, cos, sin, tan};
-use crate::types::{BasicToken, Fraction, MathError, Token};
+use crate::nsolve::{nsolve, roots};
+use crate::number::{compare, div};
+use crate::polynomial::Polynomial;
+use crate::precision::{self, inexact, track, LossReason, Operation, PrecisionLoss};
+use crate::registry::{Arity, Builtin};
+use crate::series::{closed_form, Series, CLOSED_FORM_TERMS, MAX_TERMS};
+use crate::solve::{solve, Root, Solutions};
+use crate::steps::{record_steps, Step};
+use crate::symbolic::{symbolic_eval, Expr};
+use crate::types::{BasicToken, EvalError, Fraction, MathError, Span, Token};
+use crate::value::{self, Value};
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
@@ -25,10 +42,98 @@ lazy_static! {
     };
 }
 
-fn token_eval(pair: Pair<Rule>) -> Result<Token, MathError> {
+pub(crate) fn span_of(pair: &Pair<Rule>) -> Span {
+    let span = pair.as_span();
+    Span::new(span.start(), span.end())
+}
+
+pub(crate) fn error_at(kind: MathError, pair: &Pair<Rule>) -> EvalError {
+    EvalError::new(kind).at(span_of(pair), pair.as_str())
+}
+
+/// The numbers among `values`, used as the operands of an error.
+pub(crate) fn numbers(values: &[Value]) -> Vec<Token> {
+    values
+        .iter()
+        .filter_map(Value::as_number)
+        .cloned()
+        .collect()
+}
+
+fn token_eval(pair: Pair<Rule>, env: &Environment) -> Result<Value, EvalError> {
+    match pair.as_rule() {
+        Rule::expr => value_eval(pair.into_inner(), env),
+        // Names without a value stay symbolic.
+        Rule::ident => Ok(match env.get(pair.as_str()) {
+            Some(value) => value.clone(),
+            None => match env.registry().constant(pair.as_str()) {
+                Some(constant) => Value::Number(constant.clone()),
+                None => Value::Polynomial(Polynomial::symbol(pair.as_str())),
+            },
+        }),
+        Rule::answer => answer_eval(&pair, env).cloned(),
+        Rule::call => call_eval(pair, env),
+        Rule::vector => vector_eval(pair, env),
+        Rule::list => pair
+            .clone()
+            .into_inner()
+            .map(|element| {
+                token_eval(element, env)?
+                    .number()
+                    .map_err(|kind| error_at(kind, &pair))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::List),
+        Rule::root => {
+            let mut root = pair.clone().into_inner();
+            let power = match root.next().unwrap().as_rule() {
+                Rule::sqrt_sign => BasicToken::fraction(0, 1, 2),
+                _ => BasicToken::fraction(0, 1, 3),
+            };
+            let base = token_eval(root.next().unwrap(), env)?;
+            let operands = numbers(std::slice::from_ref(&base));
+            value::exp(base, Value::Number(Token::Basic(power)))
+                .map_err(|kind| error_at(kind, &pair).with_operands(operands))
+        }
+        _ => {
+            let error_pair = pair.clone();
+            number_eval(pair)
+                .map(Value::Number)
+                .map_err(|kind| error_at(kind, &error_pair))
+        }
+    }
+}
+
+/// A list of numbers is a column vector, a list of vectors has them as its rows.
+fn vector_eval(pair: Pair<Rule>, env: &Environment) -> Result<Value, EvalError> {
+    let elements = pair
+        .clone()
+        .into_inner()
+        .map(|element| token_eval(element, env))
+        .collect::<Result<Vec<_>, _>>()?;
+    let matrix = match &elements[0] {
+        Value::Matrix(_) => elements
+            .into_iter()
+            .map(|element| match element {
+                Value::Matrix(row) => row.as_vector().map(<[Token]>::to_vec),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(MathError::NotANumber)
+            .and_then(Matrix::new),
+        _ => elements
+            .into_iter()
+            .map(Value::number)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Matrix::column),
+    };
+    matrix
+        .map(Value::Matrix)
+        .map_err(|kind| error_at(kind, &pair))
+}
+
+pub(crate) fn number_eval(pair: Pair<Rule>) -> Result<Token, MathError> {
     match pair.as_rule() {
-        Rule::expr => eval(pair.into_inner()),
-        Rule::func => fn_eval(pair.into_inner()),
         Rule::int => {
             let entire_int = pair.as_str();
             let mut pairs = pair.into_inner();
@@ -153,7 +258,7 @@ fn token_eval(pair: Pair<Rule>) -> Result<Token, MathError> {
                 Err(_) => match_string_to_float(entire_dec),
             }
         }
-        Rule::pi => Ok(match token_eval(pair.into_inner().next().unwrap())? {
+        Rule::pi => Ok(match number_eval(pair.into_inner().next().unwrap())? {
             Token::Basic(BasicToken::Integer(0)) => Token::Basic(BasicToken::Integer(0)),
             Token::Basic(x) => Token::Pi(x),
             _ => unreachable!(),
@@ -163,78 +268,955 @@ fn token_eval(pair: Pair<Rule>) -> Result<Token, MathError> {
     }
 }
 
-pub fn eval(expression: Pairs<Rule>) -> Result<Token, MathError> {
+/// Looks up `ans`, `ans(n)` or `$n` in the history.
+pub(crate) fn answer_eval<'a>(
+    pair: &Pair<Rule>,
+    env: &'a Environment,
+) -> Result<&'a Value, EvalError> {
+    let answer = match pair.clone().into_inner().next() {
+        Some(number) => number
+            .as_str()
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| env.answer(number)),
+        None => env.last_answer(),
+    };
+    answer.ok_or_else(|| error_at(MathError::UndefinedAnswer, pair))
+}
+
+/// Evaluates an expression to a number, see [`value_eval`] for expressions with symbols.
+pub fn eval(expression: Pairs<Rule>, env: &Environment) -> Result<Token, EvalError> {
+    Ok(value_eval(expression, env)?.number()?)
+}
+
+/// Evaluates an expression that may contain symbols without a value.
+pub fn value_eval(expression: Pairs<Rule>, env: &Environment) -> Result<Value, EvalError> {
     PREC_CLIMBER.climb(
         expression,
-        token_eval,
-        |lhs: Result<Token, MathError>, op: Pair<Rule>, rhs: Result<Token, MathError>| match op
-            .as_rule()
-        {
-            Rule::add => add(lhs?, rhs?),
-            Rule::subtract => sub(lhs?, rhs?),
-            Rule::multiply => mul(lhs?, rhs?),
-            Rule::divide => div(lhs?, rhs?),
-            Rule::power => exp(lhs?, rhs?),
-            _ => unreachable!(),
+        |pair| token_eval(pair, env),
+        |lhs: Result<Value, EvalError>, op: Pair<Rule>, rhs: Result<Value, EvalError>| {
+            let (lhs, rhs) = (lhs?, rhs?);
+            let operands = numbers(&[lhs.clone(), rhs.clone()]);
+            match op.as_rule() {
+                Rule::add => value::add(lhs, rhs),
+                Rule::subtract => value::sub(lhs, rhs),
+                Rule::multiply => value::mul(lhs, rhs),
+                Rule::divide => value::div(lhs, rhs),
+                Rule::power => value::exp(lhs, rhs),
+                _ => unreachable!(),
+            }
+            .map_err(|kind| error_at(kind, &op).with_operands(operands))
         },
     )
 }
 
-fn fn_eval(mut function: Pairs<Rule>) -> Result<Token, MathError> {
-    match function.next().unwrap().as_rule() {
-        Rule::sqrt => exp(
-            eval(function.next().unwrap().into_inner())?,
-            Token::Basic(BasicToken::fraction(0, 1, 2)),
-        ),
-        Rule::cbrt => exp(
-            eval(function.next().unwrap().into_inner())?,
-            Token::Basic(BasicToken::fraction(0, 1, 3)),
-        ),
+/// Parses and evaluates a single calculation.
+pub fn calculate(input: &str) -> Result<Token, EvalError> {
+    eval(
+        Expression::parse(Rule::calculation, input)?,
+        &Environment::default(),
+    )
+}
 
-        Rule::square => exp(
-            eval(function.next().unwrap().into_inner())?,
-            Token::Basic(BasicToken::Integer(2)),
-        ),
-        Rule::cube => exp(
-            eval(function.next().unwrap().into_inner())?,
-            Token::Basic(BasicToken::Integer(3)),
+/// Evaluates `;` separated statements in order, storing assignments and definitions in `env`.
+///
+/// Returns the value of the last statement, or `None` if it defined a function or set the seed.
+pub fn run(input: &str, env: &mut Environment) -> Result<Option<Value>, EvalError> {
+    execute(input, env, value_eval)
+}
+
+fn execute(
+    input: &str,
+    env: &mut Environment,
+    eval: fn(Pairs<Rule>, &Environment) -> Result<Value, EvalError>,
+) -> Result<Option<Value>, EvalError> {
+    let mut result = None;
+    for statement in Expression::parse(Rule::statements, input)? {
+        match statement.as_rule() {
+            Rule::assignment => {
+                let mut assignment = statement.into_inner();
+                let name = reserve(assignment.next().unwrap(), env)?;
+                let value = eval(assignment.next().unwrap().into_inner(), env)?;
+                env.set(name, value.clone());
+                result = Some(value);
+            }
+            Rule::definition => {
+                let mut parts: Vec<Pair<Rule>> = statement.into_inner().collect();
+                let body = parts.pop().unwrap().as_str().to_string();
+                let params = parts[1..]
+                    .iter()
+                    .map(|param| param.as_str().to_string())
+                    .collect();
+                let name = reserve(parts[0].clone(), env)?;
+                env.define(name, Function { params, body });
+                result = None;
+            }
+            Rule::expr if is_seed(&statement) => {
+                eval(statement.into_inner(), env)?;
+                result = None;
+            }
+            Rule::expr => result = Some(eval(statement.into_inner(), env)?),
+            Rule::EOI => {}
+            _ => unreachable!(),
+        }
+    }
+    Ok(result)
+}
+
+/// Whether the statement only calls `seed`, which like a definition has no result.
+fn is_seed(statement: &Pair<Rule>) -> bool {
+    let mut terms = statement.clone().into_inner();
+    match (terms.next(), terms.next()) {
+        (Some(call), None) if call.as_rule() == Rule::call => {
+            call.into_inner().next().unwrap().as_str() == "seed"
+        }
+        _ => false,
+    }
+}
+
+/// Checks that `name` is not a registered constant or function before it is assigned to.
+fn reserve<'a>(name: Pair<'a, Rule>, env: &Environment) -> Result<&'a str, EvalError> {
+    match is_special_form(name.as_str()) || env.registry().contains(name.as_str()) {
+        true => Err(error_at(MathError::ReservedName, &name)),
+        false => Ok(name.as_str()),
+    }
+}
+
+/// What a call like `f(x)` refers to.
+pub(crate) enum Callee<'a> {
+    User(&'a Function),
+    Builtin(&'a Builtin),
+    /// A variable followed by brackets, which is a multiplication as in `a(2)`.
+    Variable(&'a Value),
+}
+
+/// Finds what `call` refers to, checking that it can take `args` arguments.
+///
+/// User functions are looked up before registered ones so that they can be redefined.
+pub(crate) fn callee<'a>(
+    call: &Pair<Rule>,
+    env: &'a Environment,
+    args: &[Value],
+) -> Result<Callee<'a>, EvalError> {
+    resolve(call, env, args.len()).map_err(|error| match error.kind {
+        MathError::ArgumentCount { .. } => error.with_operands(numbers(args)),
+        _ => error,
+    })
+}
+
+/// Like [`callee`] for `found` arguments that have not been evaluated to values.
+pub(crate) fn resolve<'a>(
+    call: &Pair<Rule>,
+    env: &'a Environment,
+    found: usize,
+) -> Result<Callee<'a>, EvalError> {
+    let name = call.clone().into_inner().next().unwrap().as_str();
+    lookup(name, call, env, found)
+}
+
+/// Finds the function called `name` for `found` arguments, errors point at `at`.
+fn lookup<'a>(
+    name: &str,
+    at: &Pair<Rule>,
+    env: &'a Environment,
+    found: usize,
+) -> Result<Callee<'a>, EvalError> {
+    let (callee, arity) = if let Some(function) = env.function(name) {
+        (Callee::User(function), Arity::Fixed(function.params.len()))
+    } else if let Some(builtin) = env.registry().function(name) {
+        (Callee::Builtin(builtin), builtin.arity)
+    } else if let Some(value) = env.get(name) {
+        (Callee::Variable(value), Arity::Fixed(1))
+    } else {
+        return Err(error_at(MathError::UndefinedFunction, at));
+    };
+    match (arity, found) {
+        (Arity::Fixed(expected), _) if expected != found => {
+            let kind = MathError::ArgumentCount { expected, found };
+            Err(error_at(kind, at))
+        }
+        (Arity::Variadic, 0) => {
+            let kind = MathError::ArgumentCount { expected: 1, found };
+            Err(error_at(kind, at))
+        }
+        _ => Ok(callee),
+    }
+}
+
+/// The environment to evaluate the body of a user function in.
+pub(crate) fn function_scope(
+    call: &Pair<Rule>,
+    env: &Environment,
+    function: &Function,
+    args: Vec<Value>,
+) -> Result<Environment, EvalError> {
+    env.call_scope(function, args)
+        .ok_or_else(|| error_at(MathError::RecursionLimit, call))
+}
+
+/// Wraps an error from the body of a user function so that it points at the call.
+pub(crate) fn body_error(error: EvalError, call: &Pair<Rule>) -> EvalError {
+    match error.kind {
+        // Only the outermost call is reported rather than every level of the recursion.
+        MathError::RecursionLimit => error_at(error.kind, call),
+        // The body is not part of the input so its positions are meaningless there.
+        _ => error_at(error.kind, call).caused_by(error.without_spans()),
+    }
+}
+
+/// Functions that work on symbolic values or need their arguments unevaluated, these are not in
+/// the registry since it only holds functions of numbers.
+pub(crate) fn is_special_form(name: &str) -> bool {
+    matches!(
+        name,
+        "expand"
+            | "simplify"
+            | "collect"
+            | "solve"
+            | "linsolve"
+            | "nsolve"
+            | "roots"
+            | "diff"
+            | "integrate"
+            | "sum"
+            | "prod"
+            | "det"
+            | "inv"
+            | "transpose"
+            | "rank"
+            | "rref"
+            | "range"
+            | "map"
+            | "filter"
+            | "reduce"
+            | "length"
+            | "if"
+            | "piecewise"
+            | "rand"
+            | "randint"
+            | "randfrac"
+            | "choice"
+            | "seed"
+    )
+}
+
+/// Whether `call` is a special form. `sum` only is one when written like `sum(i^2; i; 1; 10)`,
+/// otherwise it is the registered function that adds up its arguments.
+pub(crate) fn is_special_call(name: &str, call: &Pair<Rule>) -> bool {
+    match name {
+        "sum" => {
+            let args: Vec<Pair<Rule>> = call.clone().into_inner().skip(1).collect();
+            args.len() == 4 && symbol_arg(&args[1]).is_ok()
+        }
+        _ => is_special_form(name),
+    }
+}
+
+/// The unevaluated arguments of a special form, checking that there are `N` of them.
+pub(crate) fn special_args<'i, const N: usize>(
+    call: &Pair<'i, Rule>,
+) -> Result<[Pair<'i, Rule>; N], EvalError> {
+    let args: Vec<Pair<Rule>> = call.clone().into_inner().skip(1).collect();
+    let found = args.len();
+    <[Pair<Rule>; N]>::try_from(args).map_err(|_| {
+        let kind = MathError::ArgumentCount { expected: N, found };
+        error_at(kind, call)
+    })
+}
+
+/// The arguments of a special form followed by its optional last argument.
+pub(crate) type OptionalArgs<'i, const N: usize> = ([Pair<'i, Rule>; N], Option<Pair<'i, Rule>>);
+
+/// Like [`special_args`] for special forms whose last argument can be left out.
+pub(crate) fn optional_args<'i, const N: usize>(
+    call: &Pair<'i, Rule>,
+) -> Result<OptionalArgs<'i, N>, EvalError> {
+    let mut args: Vec<Pair<Rule>> = call.clone().into_inner().skip(1).collect();
+    let found = args.len();
+    let last = match found == N + 1 {
+        true => args.pop(),
+        false => None,
+    };
+    let args = <[Pair<Rule>; N]>::try_from(args).map_err(|_| {
+        let expected = if found < N { N } else { N + 1 };
+        error_at(MathError::ArgumentCount { expected, found }, call)
+    })?;
+    Ok((args, last))
+}
+
+/// The name of the symbol an argument such as the `x` in `collect(p; x)` refers to.
+pub(crate) fn symbol_arg<'i>(arg: &Pair<'i, Rule>) -> Result<&'i str, EvalError> {
+    let mut inner = arg.clone().into_inner();
+    match (inner.next(), inner.next()) {
+        (Some(symbol), None) if symbol.as_rule() == Rule::ident => Ok(symbol.as_str()),
+        _ => Err(error_at(MathError::ExpectedSymbol, arg)),
+    }
+}
+
+/// Evaluates `lhs = rhs` as `lhs - rhs`, or a plain expression as it is.
+fn equation_eval(arg: Pair<Rule>, env: &Environment) -> Result<Value, EvalError> {
+    match arg.as_rule() {
+        Rule::comparison => return Err(error_at(MathError::UnexpectedComparison, &arg)),
+        Rule::equation => {}
+        _ => return value_eval(arg.into_inner(), env),
+    }
+    let mut sides = arg.clone().into_inner();
+    let lhs = value_eval(sides.next().unwrap().into_inner(), env)?;
+    let rhs = value_eval(sides.next().unwrap().into_inner(), env)?;
+    let operands = numbers(&[lhs.clone(), rhs.clone()]);
+    value::sub(lhs, rhs).map_err(|kind| error_at(kind, &arg).with_operands(operands))
+}
+
+/// The difference between the sides of `equation` as a polynomial in `symbol`.
+fn difference_eval(
+    equation: Pair<Rule>,
+    symbol: &str,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Result<Polynomial, EvalError> {
+    equation_eval(equation, &env.symbol_scope(symbol))
+        .and_then(|value| value.polynomial().map_err(|kind| error_at(kind, call)))
+        .map_err(|error| match error.kind {
+            // Equations such as cos(x) = x are not polynomials.
+            MathError::NotANumber | MathError::NotPolynomial => {
+                error_at(MathError::NoClosedForm, call).caused_by(error)
+            }
+            _ => error_at(error.kind, call).caused_by(error),
+        })
+}
+
+/// The exact solutions of `equation` if there is a closed form, numerical methods are used
+/// otherwise.
+fn exact_solutions(
+    equation: Pair<Rule>,
+    symbol: &str,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Option<Solutions> {
+    let difference = difference_eval(equation, symbol, call, env).ok()?;
+    solve(&difference, symbol).ok()
+}
+
+/// The antiderivative of `arg` in `symbol` if one is known.
+fn exact_antiderivative(
+    arg: Pair<Rule>,
+    symbol: &str,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Option<Expr> {
+    // Polynomials come back expanded so that they are integrated term by term.
+    let integrand = match arg_eval(arg.clone(), call, env) {
+        Ok(value) => Expr::try_from(value).ok()?,
+        Err(_) => symbolic_eval(arg, symbol, env).ok()?,
+    };
+    integrand.antiderivative(symbol).ok()
+}
+
+/// Evaluates an argument that has to be a number, like the start of `nsolve`.
+fn number_arg(arg: Pair<Rule>, call: &Pair<Rule>, env: &Environment) -> Result<Token, EvalError> {
+    arg_eval(arg, call, env)?
+        .number()
+        .map_err(|kind| error_at(kind, call))
+}
+
+fn matrix_arg(arg: Pair<Rule>, call: &Pair<Rule>, env: &Environment) -> Result<Matrix, EvalError> {
+    match arg_eval(arg.clone(), call, env)? {
+        Value::Matrix(matrix) => Ok(matrix),
+        _ => Err(error_at(MathError::ExpectedMatrix, &arg)),
+    }
+}
+
+/// Whether `ordering` satisfies a comparator such as `<=`, or `=` for an equation.
+pub(crate) fn holds(comparator: &str, ordering: Ordering) -> bool {
+    match comparator {
+        "=" => ordering == Ordering::Equal,
+        "<" => ordering == Ordering::Less,
+        ">" => ordering == Ordering::Greater,
+        "<=" | "≤" => ordering != Ordering::Greater,
+        ">=" | "≥" => ordering != Ordering::Less,
+        _ => ordering != Ordering::Equal,
+    }
+}
+
+/// The value of `if(cond; a; b)` or `piecewise(cond1; a; cond2; b; ...; default)` whose
+/// condition holds first, or the default at the end. `holds` decides a condition.
+pub(crate) fn branch<'i>(
+    name: &str,
+    call: &Pair<'i, Rule>,
+    mut holds: impl FnMut(Pair<'i, Rule>) -> Result<bool, EvalError>,
+) -> Result<Pair<'i, Rule>, EvalError> {
+    let args: Vec<Pair<Rule>> = match name {
+        "if" => special_args::<3>(call)?.into(),
+        _ => call.clone().into_inner().skip(1).collect(),
+    };
+    if args.len().is_multiple_of(2) {
+        let kind = MathError::ArgumentCount {
+            expected: args.len() + 1,
+            found: args.len(),
+        };
+        return Err(error_at(kind, call));
+    }
+    for case in args.chunks(2) {
+        match case {
+            [condition, value] if holds(condition.clone())? => return Ok(value.clone()),
+            [default] => return Ok(default.clone()),
+            _ => {}
+        }
+    }
+    unreachable!()
+}
+
+/// Whether a condition of `if` or `piecewise` holds, a plain number holds if it is not zero.
+fn condition_eval(
+    arg: Pair<Rule>,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Result<bool, EvalError> {
+    let (lhs, comparator, rhs) = match arg.as_rule() {
+        Rule::equation | Rule::comparison => {
+            let mut inner = arg.clone().into_inner();
+            let lhs = inner.next().unwrap();
+            let comparator = match arg.as_rule() {
+                Rule::equation => "=",
+                _ => inner.next().unwrap().as_str(),
+            };
+            let rhs = number_arg(inner.next().unwrap(), call, env)?;
+            (number_arg(lhs, call, env)?, comparator, rhs)
+        }
+        _ => (
+            number_arg(arg.clone(), call, env)?,
+            "!=",
+            Token::Basic(BasicToken::Integer(0)),
         ),
-        Rule::sin => sin(eval(function.next().unwrap().into_inner())?),
-        Rule::cos => cos(eval(function.next().unwrap().into_inner())?),
-        Rule::tan => tan(eval(function.next().unwrap().into_inner())?),
-        Rule::asin => asin(eval(function.next().unwrap().into_inner())?),
-        Rule::acos => acos(eval(function.next().unwrap().into_inner())?),
-        Rule::atan => atan(eval(function.next().unwrap().into_inner())?),
-        Rule::min => Ok(function
-            .try_fold(
-                (f64::INFINITY, Token::Basic(BasicToken::Integer(0))),
-                |acc: (f64, Token), pair: Pair<'_, Rule>| {
-                    let token = eval(pair.into_inner())?;
-                    let double = token.double();
-                    Ok(if double < acc.0 { (double, token) } else { acc })
-                },
-            )?
-            .1),
-        Rule::max => Ok(function
-            .try_fold(
-                (f64::NEG_INFINITY, Token::Basic(BasicToken::Integer(0))),
-                |acc: (f64, Token), pair: Pair<'_, Rule>| {
-                    let token = eval(pair.into_inner())?;
-                    let double = token.double();
-                    Ok(if double > acc.0 { (double, token) } else { acc })
-                },
-            )?
-            .1),
+    };
+    compare(&lhs, &rhs)
+        .map(|ordering| holds(comparator, ordering))
+        .map_err(|kind| error_at(kind, &arg))
+}
+
+fn list_arg(
+    arg: Pair<Rule>,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Result<Vec<Token>, EvalError> {
+    match arg_eval(arg.clone(), call, env)? {
+        Value::List(list) => Ok(list),
+        _ => Err(error_at(MathError::ExpectedList, &arg)),
+    }
+}
+
+/// Calls the function an argument names, like the `f` in `map(f; list)`.
+fn apply(
+    function: &Pair<Rule>,
+    args: Vec<Value>,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Result<Token, EvalError> {
+    let name = symbol_arg(function)?;
+    let callee = match lookup(name, function, env, args.len())? {
+        Callee::Variable(_) => return Err(error_at(MathError::UndefinedFunction, function)),
+        callee => callee,
+    };
+    invoke(callee, args, call, env)?
+        .number()
+        .map_err(|kind| error_at(kind, call))
+}
+
+/// Evaluates an argument that has to be a whole number, like the bounds of `sum`.
+fn integer_arg(arg: Pair<Rule>, call: &Pair<Rule>, env: &Environment) -> Result<i128, EvalError> {
+    match number_arg(arg.clone(), call, env)? {
+        Token::Basic(BasicToken::Integer(i)) => Ok(i),
+        _ => Err(error_at(MathError::ExpectedInteger, &arg)),
+    }
+}
+
+fn special_eval(name: &str, call: Pair<Rule>, env: &Environment) -> Result<Value, EvalError> {
+    match name {
+        // Polynomials are always kept expanded with like terms combined.
+        "expand" | "simplify" => {
+            let [arg] = special_args(&call)?;
+            Ok(match arg_eval(arg, &call, env)? {
+                Value::Polynomial(polynomial) => Value::Polynomial(polynomial.expand()),
+                number => number,
+            })
+        }
+        "collect" => {
+            let [arg, symbol] = special_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            Ok(match arg_eval(arg, &call, env)? {
+                Value::Polynomial(polynomial) => Value::Polynomial(polynomial.collect(symbol)),
+                number => number,
+            })
+        }
+        "solve" => {
+            let [equation, symbol] = special_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            let difference = difference_eval(equation, symbol, &call, env)?;
+            solve(&difference, symbol)
+                .map(Value::Solutions)
+                .map_err(|kind| error_at(kind, &call))
+        }
+        "linsolve" => {
+            let equations = call
+                .clone()
+                .into_inner()
+                .skip(1)
+                .map(|equation| {
+                    equation_eval(equation, env)
+                        .and_then(|value| value.polynomial().map_err(|kind| error_at(kind, &call)))
+                        .map_err(|error| match error.kind {
+                            // Equations such as sin(x) = y are not linear either.
+                            MathError::NotANumber | MathError::NotPolynomial => {
+                                error_at(MathError::NotLinear, &call).caused_by(error)
+                            }
+                            _ => error_at(error.kind, &call).caused_by(error),
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            linsolve(&equations)
+                .map(Value::LinearSolutions)
+                .map_err(|kind| error_at(kind, &call))
+        }
+        "nsolve" => {
+            let [equation, symbol, start] = special_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            let start = number_arg(start, &call, env)?;
+            // The exact root closest to the start is used if there is one.
+            let exact = exact_solutions(equation.clone(), symbol, &call, env);
+            if let Some(solutions) = exact.filter(|solutions| !solutions.identity) {
+                let closest = solutions.roots.into_iter().min_by(|a, b| {
+                    let (a, b) = (a.value.double(), b.value.double());
+                    let (a, b) = ((a - start.double()).abs(), (b - start.double()).abs());
+                    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+                });
+                if let Some(root) = closest {
+                    return Ok(Value::Number(root.value));
+                }
+            }
+            let f = float_function(equation, symbol, env);
+            let (root, convergence) = nsolve(f, start.double())
+                .map_err(|error| error_at(error.kind, &call).caused_by(error))?;
+            let operation = Operation::RootFinding(convergence);
+            inexact(operation, vec![], LossReason::NoClosedForm, root)
+                .map(Value::Number)
+                .map_err(|kind| error_at(kind, &call))
+        }
+        "diff" => {
+            let ([expression, symbol], point) = optional_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            let mut scope = env.symbol_scope(symbol);
+            let derivative = symbolic_eval(expression, symbol, &scope)
+                .map_err(|error| error_at(error.kind, &call).caused_by(error))?
+                .derivative(symbol)
+                .map_err(|kind| error_at(kind, &call))?;
+            match point {
+                None => Ok(derivative.simplify(&scope)),
+                Some(point) => {
+                    scope.set(symbol, arg_eval(point, &call, env)?);
+                    derivative
+                        .eval(&scope)
+                        .map_err(|kind| error_at(kind, &call))
+                }
+            }
+        }
+        "integrate" => {
+            let [expression, symbol, a, b] = special_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            if expression.as_rule() == Rule::equation {
+                return Err(error_at(MathError::UnexpectedEquation, &expression));
+            }
+            let (a, b) = (number_arg(a, &call, env)?, number_arg(b, &call, env)?);
+            let mut scope = env.symbol_scope(symbol);
+            if let Some(antiderivative) =
+                exact_antiderivative(expression.clone(), symbol, &call, &scope)
+            {
+                match antiderivative.check_between(symbol, (&a, &b), &scope) {
+                    // Where the antiderivative holds is unknown, the quadrature below decides.
+                    Err(MathError::NoClosedForm) => {}
+                    Err(kind) => return Err(error_at(kind, &call)),
+                    Ok(()) => {
+                        scope.set(symbol, Value::Number(b.clone()));
+                        let upper = antiderivative.eval(&scope);
+                        scope.set(symbol, Value::Number(a.clone()));
+                        let lower = antiderivative.eval(&scope);
+                        return upper
+                            .and_then(|upper| value::sub(upper, lower?))
+                            .map_err(|kind| error_at(kind, &call));
+                    }
+                }
+            }
+            let f = float_function(expression, symbol, env);
+            let (value, convergence) = integrate(f, a.double(), b.double())
+                .map_err(|error| error_at(error.kind, &call).caused_by(error))?;
+            let operation = Operation::Integration(convergence);
+            inexact(operation, vec![], LossReason::NoAntiderivative, value)
+                .map(Value::Number)
+                .map_err(|kind| error_at(kind, &call))
+        }
+        "sum" | "prod" => {
+            let [expression, index, from, to] = special_args(&call)?;
+            let index = symbol_arg(&index)?;
+            let (from, to) = (integer_arg(from, &call, env)?, integer_arg(to, &call, env)?);
+            let (series, empty) = match name {
+                "sum" => (Series::Sum, 0),
+                _ => (Series::Product, 1),
+            };
+            let mut result = Value::Number(Token::Basic(BasicToken::Integer(empty)));
+            if from > to {
+                return Ok(result);
+            }
+            let mut scope = env.symbol_scope(index);
+            // One less than the number of terms, which may not fit in an i128.
+            let steps = to.checked_sub(from).unwrap_or(i128::MAX);
+            if steps >= CLOSED_FORM_TERMS {
+                if let Ok(term) = symbolic_eval(expression.clone(), index, &scope) {
+                    let closed = closed_form(series, &term, index, (from, to), env)
+                        .map_err(|kind| error_at(kind, &call))?;
+                    if let Some(value) = closed {
+                        return Ok(value);
+                    }
+                }
+                if steps >= MAX_TERMS {
+                    return Err(error_at(MathError::TooManyTerms, &call));
+                }
+            }
+            for i in from..=to {
+                scope.set(index, Value::Number(Token::Basic(BasicToken::Integer(i))));
+                let term = arg_eval(expression.clone(), &call, &scope)?;
+                result = match series {
+                    Series::Sum => value::add(result, term),
+                    Series::Product => value::mul(result, term),
+                }
+                .map_err(|kind| error_at(kind, &call))?;
+            }
+            Ok(result)
+        }
+        "det" | "inv" | "transpose" | "rank" | "rref" => {
+            let [arg] = special_args(&call)?;
+            let matrix = matrix_arg(arg, &call, env)?;
+            match name {
+                "det" => matrix.det().map(Value::Number),
+                "inv" => matrix.inverse().map(Value::Matrix),
+                "transpose" => Ok(Value::Matrix(matrix.transpose())),
+                "rank" => Ok(Value::Number(Token::Basic(BasicToken::Integer(
+                    matrix.rank()? as i128,
+                )))),
+                _ => matrix.rref().map(Value::Matrix),
+            }
+            .map_err(|kind| error_at(kind, &call))
+        }
+        // Only the branch that is used is evaluated.
+        "if" | "piecewise" => {
+            let branch = branch(name, &call, |condition| {
+                condition_eval(condition, &call, env)
+            })?;
+            arg_eval(branch, &call, env)
+        }
+        "rand" => {
+            let [] = special_args(&call)?;
+            let value = env.random().unit();
+            inexact(Operation::Random, vec![], LossReason::Random, value)
+                .map(Value::Number)
+                .map_err(|kind| error_at(kind, &call))
+        }
+        "randint" => {
+            let [low, high] = special_args(&call)?;
+            let (low, high) = (
+                integer_arg(low, &call, env)?,
+                integer_arg(high, &call, env)?,
+            );
+            if low > high {
+                return Err(error_at(MathError::DomainError, &call));
+            }
+            let value = env.random().integer(low, high);
+            Ok(Value::Number(Token::Basic(BasicToken::Integer(value))))
+        }
+        // A fraction between 0 and 1 whose denominator is at most maxden, in lowest terms.
+        "randfrac" => {
+            let [max_den] = special_args(&call)?;
+            let max_den = integer_arg(max_den, &call, env)?;
+            if max_den < 2 {
+                return Err(error_at(MathError::DomainError, &call));
+            }
+            let den = env.random().integer(2, max_den);
+            let num = env.random().integer(1, den - 1);
+            let fraction = Token::Basic(BasicToken::Integer(num));
+            div(fraction, Token::Basic(BasicToken::Integer(den)))
+                .map(Value::Number)
+                .map_err(|kind| error_at(kind, &call))
+        }
+        "choice" => {
+            let [list] = special_args(&call)?;
+            let list = list_arg(list, &call, env)?;
+            if list.is_empty() {
+                return Err(error_at(MathError::EmptyList, &call));
+            }
+            let index = env.random().integer(0, list.len() as i128 - 1);
+            Ok(Value::Number(list[index as usize].clone()))
+        }
+        "seed" => {
+            let [seed] = special_args(&call)?;
+            let value = integer_arg(seed.clone(), &call, env)?;
+            let seed = u64::try_from(value).map_err(|_| error_at(MathError::DomainError, &seed))?;
+            env.random().reseed(seed);
+            Ok(Value::Number(Token::Basic(BasicToken::Integer(value))))
+        }
+        "range" => {
+            let ([from, to], step) = optional_args(&call)?;
+            let (from, to) = (number_arg(from, &call, env)?, number_arg(to, &call, env)?);
+            let step = match step {
+                Some(step) => number_arg(step, &call, env)?,
+                None => Token::Basic(BasicToken::Integer(1)),
+            };
+            range(from, to, step)
+                .map(Value::List)
+                .map_err(|kind| error_at(kind, &call))
+        }
+        "map" => {
+            let [function, list] = special_args(&call)?;
+            list_arg(list, &call, env)?
+                .into_iter()
+                .map(|element| apply(&function, vec![Value::Number(element)], &call, env))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::List)
+        }
+        // Keeps the elements for which the function is not zero.
+        "filter" => {
+            let [function, list] = special_args(&call)?;
+            let mut kept = vec![];
+            for element in list_arg(list, &call, env)? {
+                let keep = apply(&function, vec![Value::Number(element.clone())], &call, env)?;
+                if !keep.is_zero() {
+                    kept.push(element);
+                }
+            }
+            Ok(Value::List(kept))
+        }
+        // Combines the elements from left to right, starting with the first one if no start
+        // is given.
+        "reduce" => {
+            let ([function, list], start) = optional_args(&call)?;
+            let mut elements = list_arg(list, &call, env)?.into_iter();
+            let mut result = match start {
+                Some(start) => number_arg(start, &call, env)?,
+                None => elements
+                    .next()
+                    .ok_or_else(|| error_at(MathError::EmptyList, &call))?,
+            };
+            for element in elements {
+                let args = vec![Value::Number(result), Value::Number(element)];
+                result = apply(&function, args, &call, env)?;
+            }
+            Ok(Value::Number(result))
+        }
+        "length" => {
+            let [list] = special_args(&call)?;
+            let length = list_arg(list, &call, env)?.len();
+            Ok(Value::Number(Token::Basic(BasicToken::Integer(
+                length as i128,
+            ))))
+        }
+        "roots" => {
+            let [equation, symbol, a, b] = special_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            let (a, b) = (number_arg(a, &call, env)?, number_arg(b, &call, env)?);
+            let (a, b) = (a.double().min(b.double()), a.double().max(b.double()));
+            if let Some(mut solutions) = exact_solutions(equation.clone(), symbol, &call, env) {
+                solutions.roots.retain(|root| {
+                    let value = root.value.double();
+                    a <= value && value <= b
+                });
+                return Ok(Value::Solutions(solutions));
+            }
+            let f = float_function(equation, symbol, env);
+            let (found, convergence) =
+                roots(f, a, b).map_err(|error| error_at(error.kind, &call).caused_by(error))?;
+            if !found.is_empty() {
+                let operation = Operation::RootFinding(convergence);
+                precision::record(operation, vec![], LossReason::NoClosedForm)
+                    .map_err(|kind| error_at(kind, &call))?;
+            }
+            Ok(Value::Solutions(Solutions {
+                symbol: symbol.to_string(),
+                roots: found
+                    .into_iter()
+                    .map(|root| Root {
+                        value: Token::Basic(BasicToken::Double(root)),
+                        multiplicity: 1,
+                    })
+                    .collect(),
+                complex: 0,
+                identity: false,
+            }))
+        }
         _ => unreachable!(),
     }
 }
 
+pub(crate) fn call_eval(call: Pair<Rule>, env: &Environment) -> Result<Value, EvalError> {
+    let name = call.clone().into_inner().next().unwrap().as_str();
+    if is_special_call(name, &call) {
+        return special_eval(name, call, env);
+    }
+    let args = call
+        .clone()
+        .into_inner()
+        .skip(1)
+        .map(|arg| arg_eval(arg, &call, env))
+        .collect::<Result<Vec<_>, _>>()?;
+    invoke(callee(&call, env, &args)?, args, &call, env)
+}
+
+/// Calls what `call` refers to with the evaluated `args`.
+fn invoke(
+    callee: Callee,
+    args: Vec<Value>,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Result<Value, EvalError> {
+    match callee {
+        Callee::User(function) => {
+            let scope = function_scope(call, env, function, args)?;
+            value_eval(
+                Expression::parse(Rule::calculation, &function.body)?,
+                &scope,
+            )
+            .map_err(|error| body_error(error, call))
+        }
+        Callee::Builtin(builtin) => {
+            let mut numbers = Vec::with_capacity(args.len());
+            for arg in args {
+                match arg {
+                    // Functions such as max and mean take the elements of a list.
+                    Value::List(list) if builtin.arity == Arity::Variadic => numbers.extend(list),
+                    arg => numbers.push(arg.number().map_err(|kind| error_at(kind, call))?),
+                }
+            }
+            if numbers.is_empty() {
+                return Err(error_at(MathError::EmptyList, call));
+            }
+            builtin
+                .call(&numbers)
+                .map(Value::Number)
+                .map_err(|kind| error_at(kind, call).with_operands(numbers))
+        }
+        Callee::Variable(value) => {
+            let operands = numbers(&[value.clone(), args[0].clone()]);
+            value::mul(value.clone(), args[0].clone())
+                .map_err(|kind| error_at(kind, call).with_operands(operands))
+        }
+    }
+}
+
+/// The result of a calculation along with the places where it had to be rounded.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Evaluation {
+    /// `None` when the input ended with a function definition.
+    pub result: Option<Value>,
+    pub warnings: Vec<PrecisionLoss>,
+    /// How the result was simplified, only filled in when [`EvalOptions::steps`] is set.
+    pub steps: Vec<Step>,
+}
+
+/// How numbers are represented while evaluating.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum Mode {
+    /// Use the exact types, falling back to a double when a value cannot be stored exactly.
+    #[default]
+    Exact,
+    /// Use the exact types and fail when a value cannot be stored exactly.
+    Strict,
+    /// Only use f64, see [`crate::float`].
+    Float,
+}
+
+/// Settings that change how a calculation is evaluated.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct EvalOptions {
+    pub mode: Mode,
+    /// Record the simplification steps, this has no effect with [`Mode::Float`].
+    pub steps: bool,
+    /// Read the input as LaTeX, see [`crate::latex::translate`].
+    pub latex: bool,
+}
+
+/// Like [`run`] but also reports every operation that could not be computed exactly.
+///
+/// The result is added to the history of `env`.
+pub fn evaluate(
+    input: &str,
+    env: &mut Environment,
+    options: &EvalOptions,
+) -> Result<Evaluation, EvalError> {
+    if options.latex {
+        let input = translate(input)?;
+        let options = EvalOptions {
+            latex: false,
+            ..*options
+        };
+        // The positions of errors would point into the translation and not the input.
+        return evaluate(&input, env, &options).map_err(EvalError::without_spans);
+    }
+    if options.mode == Mode::Float {
+        let result = execute(input, env, |expression, env| {
+            Ok(Value::Number(Token::Basic(BasicToken::Double(eval_float(
+                expression, env,
+            )?))))
+        })?;
+        if let Some(result) = &result {
+            env.push_answer(result.clone());
+        }
+        return Ok(Evaluation {
+            result,
+            warnings: vec![],
+            steps: vec![],
+        });
+    }
+    let strict = options.mode == Mode::Strict;
+    let ((result, warnings), steps) = match options.steps {
+        true => record_steps(|| track(strict, || run(input, env))),
+        false => (track(strict, || run(input, env)), vec![]),
+    };
+    let result = result?;
+    if let Some(result) = &result {
+        env.push_answer(result.clone());
+    }
+    Ok(Evaluation {
+        result,
+        warnings,
+        steps,
+    })
+}
+
+impl<R: pest::RuleType> From<pest::error::Error<R>> for EvalError {
+    fn from(error: pest::error::Error<R>) -> EvalError {
+        let span = match error.location {
+            InputLocation::Pos(pos) => Span::new(pos, pos),
+            InputLocation::Span((start, end)) => Span::new(start, end),
+        };
+        EvalError::new(MathError::SyntaxError).at(span, "")
+    }
+}
+
+/// Evaluates an argument of a function call, wrapping any error so that it points at the call.
+fn arg_eval(arg: Pair<Rule>, call: &Pair<Rule>, env: &Environment) -> Result<Value, EvalError> {
+    match arg.as_rule() {
+        Rule::equation => return Err(error_at(MathError::UnexpectedEquation, &arg)),
+        Rule::comparison => return Err(error_at(MathError::UnexpectedComparison, &arg)),
+        _ => {}
+    }
+    value_eval(arg.into_inner(), env).map_err(|error| error_at(error.kind, call).caused_by(error))
+}
+
 #[inline]
 fn match_string_to_float(string: &str) -> Result<Token, MathError> {
     match string.parse::<f64>() {
-        Ok(i) if i != f64::INFINITY && i != f64::NEG_INFINITY => {
-            Ok(Token::Basic(BasicToken::Double(i)))
-        }
-        _ => Err(MathError::DoubleOverflow),
+        Ok(i) => inexact(
+            Operation::Literal(string.to_string()),
+            vec![],
+            LossReason::Overflow,
+            i,
+        ),
+        Err(_) => Err(MathError::DoubleOverflow),
     }
 }
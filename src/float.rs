@@ -0,0 +1,295 @@
+//! Evaluates calculations directly with `f64`, skipping the exact number types.
+//!
+//! This is much faster than [`crate::expression::eval`] and is meant for plotting and bulk
+//! evaluation. Results agree with [`Token::double`] of the exact result to within a relative
+//! error of [`TOLERANCE`], except that poles such as `tan(pi/2)` are not detected.
+
+use crate::environment::Environment;
+use crate::expression::{
+    self, answer_eval, body_error, branch, callee, error_at, function_scope, holds,
+    is_special_call, optional_args, special_args, symbol_arg, Callee, Expression, Rule,
+    PREC_CLIMBER,
+};
+use crate::integrate::integrate;
+use crate::nsolve::nsolve;
+use crate::series::MAX_TERMS;
+use crate::types::{BasicToken, EvalError, MathError, Token};
+use crate::value::Value;
+use pest::iterators::{Pair, Pairs};
+use pest::Parser;
+use std::cmp::Ordering;
+
+/// Largest relative difference between a float result and the exact result.
+pub const TOLERANCE: f64 = 1e-9;
+
+/// Checks that `float` agrees with `exact` to within [`TOLERANCE`].
+pub fn within_tolerance(exact: f64, float: f64) -> bool {
+    (exact - float).abs() <= TOLERANCE * exact.abs().max(1.0)
+}
+
+#[inline]
+fn operand(value: f64) -> Token {
+    Token::Basic(BasicToken::Double(value))
+}
+
+#[inline]
+fn check(value: f64) -> Result<f64, MathError> {
+    Ok(double_check!(value))
+}
+
+/// Symbols and solutions cannot be kept when only using f64.
+fn value_double(value: &Value, pair: &Pair<Rule>) -> Result<f64, EvalError> {
+    match value {
+        Value::Number(token) => Ok(token.double()),
+        _ => Err(error_at(MathError::NotANumber, pair)),
+    }
+}
+
+fn token_eval(pair: Pair<Rule>, env: &Environment) -> Result<f64, EvalError> {
+    match pair.as_rule() {
+        Rule::expr => eval_float(pair.into_inner(), env),
+        Rule::ident => match env.get(pair.as_str()) {
+            Some(value) => value_double(value, &pair),
+            None => env
+                .registry()
+                .constant(pair.as_str())
+                .map(Token::double)
+                .ok_or_else(|| error_at(MathError::UndefinedVariable, &pair)),
+        },
+        Rule::answer => value_double(answer_eval(&pair, env)?, &pair),
+        Rule::call => call_eval(pair, env),
+        Rule::vector | Rule::list => Err(error_at(MathError::ExactOnly, &pair)),
+        Rule::root => {
+            let mut root = pair.clone().into_inner();
+            let sign = root.next().unwrap().as_rule();
+            let base = token_eval(root.next().unwrap(), env)?;
+            match sign {
+                Rule::sqrt_sign if base < 0.0 => {
+                    Err(error_at(MathError::ComplexNumber, &pair)
+                        .with_operands(vec![operand(base)]))
+                }
+                Rule::sqrt_sign => Ok(base.sqrt()),
+                _ => Ok(base.cbrt()),
+            }
+        }
+        Rule::pi => {
+            let number = token_eval(pair.into_inner().next().unwrap(), env)?;
+            Ok(number * std::f64::consts::PI)
+        }
+        Rule::single_pi => Ok(std::f64::consts::PI),
+        // The int rule allows a trailing "." which f64 parsing also accepts.
+        Rule::int | Rule::dec => pair
+            .as_str()
+            .parse::<f64>()
+            .map_err(|_| error_at(MathError::DoubleOverflow, &pair))
+            .and_then(|value| check(value).map_err(|kind| error_at(kind, &pair))),
+        _ => unreachable!(),
+    }
+}
+
+/// Evaluates an expression using only `f64` arithmetic.
+pub fn eval_float(expression: Pairs<Rule>, env: &Environment) -> Result<f64, EvalError> {
+    PREC_CLIMBER.climb(
+        expression,
+        |pair| token_eval(pair, env),
+        |lhs: Result<f64, EvalError>, op: Pair<Rule>, rhs: Result<f64, EvalError>| {
+            let (lhs, rhs) = (lhs?, rhs?);
+            match op.as_rule() {
+                Rule::add => check(lhs + rhs),
+                Rule::subtract => check(lhs - rhs),
+                Rule::multiply => check(lhs * rhs),
+                Rule::divide if rhs == 0.0 => Err(MathError::DivisionByZero),
+                Rule::divide => check(lhs / rhs),
+                Rule::power if lhs == 0.0 && rhs == 0.0 => Err(MathError::ExponentiationError),
+                Rule::power => check(lhs.powf(rhs)),
+                _ => unreachable!(),
+            }
+            .map_err(|kind| error_at(kind, &op).with_operands(vec![operand(lhs), operand(rhs)]))
+        },
+    )
+}
+
+/// Parses and evaluates a single calculation using only `f64` arithmetic.
+pub fn calculate_float(input: &str) -> Result<f64, EvalError> {
+    eval_float(
+        Expression::parse(Rule::calculation, input)?,
+        &Environment::default(),
+    )
+}
+
+fn call_eval(call: Pair<Rule>, env: &Environment) -> Result<f64, EvalError> {
+    let name = call.clone().into_inner().next().unwrap().as_str();
+    match name {
+        "solve" | "linsolve" | "roots" => return Err(error_at(MathError::ExactOnly, &call)),
+        "nsolve" => {
+            let [equation, symbol, start] = special_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            let start = arg_eval(start, &call, env)?;
+            let f = float_function(equation, symbol, env);
+            return Ok(nsolve(f, start)
+                .map_err(|error| error_at(error.kind, &call).caused_by(error))?
+                .0);
+        }
+        "integrate" => {
+            let [expression, symbol, a, b] = special_args(&call)?;
+            let symbol = symbol_arg(&symbol)?;
+            if expression.as_rule() == Rule::equation {
+                return Err(error_at(MathError::UnexpectedEquation, &expression));
+            }
+            let (a, b) = (arg_eval(a, &call, env)?, arg_eval(b, &call, env)?);
+            let f = float_function(expression, symbol, env);
+            return Ok(integrate(f, a, b)
+                .map_err(|error| error_at(error.kind, &call).caused_by(error))?
+                .0);
+        }
+        "sum" | "prod" if is_special_call(name, &call) => {
+            let [expression, index, from, to] = special_args(&call)?;
+            let index = symbol_arg(&index)?;
+            let bound = |arg: Pair<Rule>| match arg_eval(arg.clone(), &call, env)? {
+                value if value.fract() == 0.0 => Ok(value),
+                _ => Err(error_at(MathError::ExpectedInteger, &arg)),
+            };
+            let (from, to) = (bound(from)?, bound(to)?);
+            if to - from >= MAX_TERMS as f64 {
+                return Err(error_at(MathError::TooManyTerms, &call));
+            }
+            let mut f = float_function(expression, index, env);
+            let mut result = if name == "sum" { 0.0 } else { 1.0 };
+            let mut i = from;
+            while i <= to {
+                let term = f(i).map_err(|error| error_at(error.kind, &call).caused_by(error))?;
+                result = if name == "sum" {
+                    result + term
+                } else {
+                    result * term
+                };
+                i += 1.0;
+            }
+            return check(result).map_err(|kind| error_at(kind, &call));
+        }
+        // Without symbols these only have to evaluate their first argument.
+        "expand" | "simplify" => {
+            let [arg] = special_args(&call)?;
+            return arg_eval(arg, &call, env);
+        }
+        "collect" => {
+            let [arg, _] = special_args(&call)?;
+            return arg_eval(arg, &call, env);
+        }
+        // Matrices only have exact entries.
+        "det" | "rank" => {
+            let value = expression::call_eval(call.clone(), env)?;
+            return value_double(&value, &call);
+        }
+        // These use the generator of the environment, which is the same in both modes.
+        "rand" | "randint" | "randfrac" | "seed" => {
+            let value = expression::call_eval(call.clone(), env)?;
+            return value_double(&value, &call);
+        }
+        "inv" | "transpose" | "rref" => return Err(error_at(MathError::ExactOnly, &call)),
+        "if" | "piecewise" => {
+            let branch = branch(name, &call, |condition| {
+                condition_float(condition, &call, env)
+            })?;
+            return arg_eval(branch, &call, env);
+        }
+        // Lists only have exact elements.
+        "range" | "map" | "filter" | "reduce" | "length" | "choice" => {
+            return Err(error_at(MathError::ExactOnly, &call))
+        }
+        // The derivative is found exactly, only its value at a point is a number.
+        "diff" => {
+            let (_, point) = optional_args::<2>(&call)?;
+            if point.is_none() {
+                return Err(error_at(MathError::ExactOnly, &call));
+            }
+            let value = expression::call_eval(call.clone(), env)?;
+            return value_double(&value, &call);
+        }
+        _ => {}
+    }
+    let args = call
+        .clone()
+        .into_inner()
+        .skip(1)
+        .map(|arg| arg_eval(arg, &call, env))
+        .collect::<Result<Vec<_>, _>>()?;
+    let operands: Vec<Token> = args.iter().map(|arg| operand(*arg)).collect();
+    let values: Vec<Value> = operands.iter().cloned().map(Value::Number).collect();
+    match callee(&call, env, &values)? {
+        Callee::User(function) => {
+            let scope = function_scope(&call, env, function, values)?;
+            eval_float(
+                Expression::parse(Rule::calculation, &function.body)?,
+                &scope,
+            )
+            .map_err(|error| body_error(error, &call))
+        }
+        Callee::Builtin(builtin) => builtin
+            .call_float(&args)
+            .map_err(|kind| error_at(kind, &call).with_operands(operands)),
+        Callee::Variable(value) => {
+            let value = value_double(value, &call)?;
+            check(value * args[0]).map_err(|kind| {
+                error_at(kind, &call).with_operands(vec![operand(value), operand(args[0])])
+            })
+        }
+    }
+}
+
+/// Evaluates `lhs = rhs` as `lhs - rhs`, or a plain expression as it is.
+fn equation_float(arg: Pair<Rule>, env: &Environment) -> Result<f64, EvalError> {
+    match arg.as_rule() {
+        Rule::comparison => return Err(error_at(MathError::UnexpectedComparison, &arg)),
+        Rule::equation => {}
+        _ => return eval_float(arg.into_inner(), env),
+    }
+    let mut sides = arg.into_inner();
+    let lhs = eval_float(sides.next().unwrap().into_inner(), env)?;
+    let rhs = eval_float(sides.next().unwrap().into_inner(), env)?;
+    Ok(lhs - rhs)
+}
+
+/// The difference between the sides of `equation` as a function of `symbol`, for finding roots.
+pub(crate) fn float_function<'i>(
+    equation: Pair<'i, Rule>,
+    symbol: &'i str,
+    env: &Environment,
+) -> impl FnMut(f64) -> Result<f64, EvalError> + 'i {
+    let mut scope = env.symbol_scope(symbol);
+    move |x| {
+        scope.set(symbol, Value::Number(operand(x)));
+        equation_float(equation.clone(), &scope)
+    }
+}
+
+/// Whether a condition of `if` or `piecewise` holds, comparing doubles.
+fn condition_float(
+    arg: Pair<Rule>,
+    call: &Pair<Rule>,
+    env: &Environment,
+) -> Result<bool, EvalError> {
+    let (lhs, comparator, rhs) = match arg.as_rule() {
+        Rule::equation | Rule::comparison => {
+            let mut inner = arg.clone().into_inner();
+            let lhs = arg_eval(inner.next().unwrap(), call, env)?;
+            let comparator = match arg.as_rule() {
+                Rule::equation => "=",
+                _ => inner.next().unwrap().as_str(),
+            };
+            (lhs, comparator, arg_eval(inner.next().unwrap(), call, env)?)
+        }
+        _ => (arg_eval(arg, call, env)?, "!=", 0.0),
+    };
+    let ordering = lhs.partial_cmp(&rhs).unwrap_or(Ordering::Equal);
+    Ok(holds(comparator, ordering))
+}
+
+fn arg_eval(arg: Pair<Rule>, call: &Pair<Rule>, env: &Environment) -> Result<f64, EvalError> {
+    match arg.as_rule() {
+        Rule::equation => return Err(error_at(MathError::UnexpectedEquation, &arg)),
+        Rule::comparison => return Err(error_at(MathError::UnexpectedComparison, &arg)),
+        _ => {}
+    }
+    eval_float(arg.into_inner(), env).map_err(|error| error_at(error.kind, call).caused_by(error))
+}
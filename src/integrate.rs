@@ -0,0 +1,132 @@
+//! Integrates functions numerically, for when [`crate::symbolic`] has no antiderivative.
+//!
+//! Each piece of the interval is integrated with the 15-point Gauss–Kronrod rule, the difference
+//! to the 7-point Gauss rule whose points it reuses estimates the error. The piece with the
+//! largest error is halved until the errors add up to less than the tolerance.
+
+use crate::precision::Convergence;
+use crate::types::{EvalError, MathError};
+
+/// Integration stops once the error is smaller than this relative to the result.
+pub const TOLERANCE: f64 = 1e-10;
+/// How often the interval may be split before giving up with [`MathError::NotIntegrable`].
+pub const MAX_SPLITS: u32 = 2000;
+/// A piece this small compared to the interval that still has the largest error is next to a pole.
+const POLE_WIDTH: f64 = 1e-10;
+
+/// The points of the Kronrod rule on `[-1, 1]`, the odd ones are those of the Gauss rule.
+const KRONROD_POINTS: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_2,
+    0.140_653_259_715_525_9,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_8,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+/// The integral over `[a, b]` and its estimated error.
+struct Piece {
+    a: f64,
+    b: f64,
+    value: f64,
+    error: f64,
+}
+
+/// Evaluates `f` at `x`, a value too large for a double means there is a pole and the integral
+/// diverges.
+fn sample(f: &mut impl FnMut(f64) -> Result<f64, EvalError>, x: f64) -> Result<f64, EvalError> {
+    match f(x) {
+        Ok(value) if value.is_finite() => Ok(value),
+        Ok(_) => Err(MathError::Divergent.into()),
+        Err(error)
+            if matches!(
+                error.kind,
+                MathError::DoubleOverflow | MathError::DivisionByZero
+            ) =>
+        {
+            Err(MathError::Divergent.into())
+        }
+        Err(error) => Err(error),
+    }
+}
+
+fn kronrod(
+    f: &mut impl FnMut(f64) -> Result<f64, EvalError>,
+    a: f64,
+    b: f64,
+) -> Result<Piece, EvalError> {
+    let (center, half) = ((a + b) / 2.0, (b - a) / 2.0);
+    let value = sample(f, center)?;
+    let mut kronrod = KRONROD_WEIGHTS[7] * value;
+    let mut gauss = GAUSS_WEIGHTS[3] * value;
+    for i in 0..7 {
+        let offset = half * KRONROD_POINTS[i];
+        let pair = sample(f, center - offset)? + sample(f, center + offset)?;
+        kronrod += KRONROD_WEIGHTS[i] * pair;
+        if i % 2 == 1 {
+            gauss += GAUSS_WEIGHTS[i / 2] * pair;
+        }
+    }
+    Ok(Piece {
+        a,
+        b,
+        value: kronrod * half,
+        error: ((kronrod - gauss) * half).abs(),
+    })
+}
+
+/// Integrates `f` from `a` to `b`.
+///
+/// The iterations are the number of times the interval was split.
+pub fn integrate(
+    mut f: impl FnMut(f64) -> Result<f64, EvalError>,
+    a: f64,
+    b: f64,
+) -> Result<(f64, Convergence), EvalError> {
+    let mut pieces = vec![kronrod(&mut f, a, b)?];
+    for iterations in 0..=MAX_SPLITS {
+        let value: f64 = pieces.iter().map(|piece| piece.value).sum();
+        let error: f64 = pieces.iter().map(|piece| piece.error).sum();
+        if error <= TOLERANCE * value.abs().max(1.0) {
+            return Ok((value, Convergence { iterations, error }));
+        }
+        let worst = (0..pieces.len())
+            .max_by(|i, j| pieces[*i].error.total_cmp(&pieces[*j].error))
+            .unwrap();
+        let piece = pieces.swap_remove(worst);
+        let middle = (piece.a + piece.b) / 2.0;
+        // The piece is too small to split, its error stays large because it is next to a pole.
+        if middle == piece.a || middle == piece.b {
+            return Err(MathError::Divergent.into());
+        }
+        pieces.push(kronrod(&mut f, piece.a, middle)?);
+        pieces.push(kronrod(&mut f, middle, piece.b)?);
+    }
+    // The splits crowd around a pole, where the error stays large however small the pieces get.
+    let worst = pieces
+        .iter()
+        .max_by(|p, q| p.error.total_cmp(&q.error))
+        .unwrap();
+    match (worst.b - worst.a).abs() <= POLE_WIDTH * (b - a).abs() {
+        true => Err(MathError::Divergent.into()),
+        false => Err(MathError::NotIntegrable.into()),
+    }
+}
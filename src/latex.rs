@@ -0,0 +1,219 @@
+//! LaTeX for exact results, e.g. `\frac{\sqrt{6}}{4} - \frac{\sqrt{2}}{4}`, and LaTeX input.
+//!
+//! The terms are written in the same order and with the same signs as the [`std::fmt::Display`]
+//! implementations in [`crate::types`], only the notation differs.
+//!
+//! Input such as `\frac{1}{2} + \sqrt[3]{x}` is parsed with `latex.pest` and translated into the
+//! syntax of `grammar.pest`, so it is evaluated exactly like a calculation typed by hand.
+
+use crate::expression::{calculate, Parser};
+use crate::matrix::Matrix;
+use crate::types::{double_parts, BasicToken, EvalError, Term, Token};
+use crate::value::Value;
+use pest::iterators::Pair;
+
+#[derive(Parser)]
+#[grammar = "latex.pest"]
+pub struct LatexExpression;
+
+impl Token {
+    /// The number as LaTeX, with fractions as `\frac{}{}`, roots as `\sqrt{}` or `\sqrt[3]{}`
+    /// and π as `\pi`.
+    pub fn to_latex(&self) -> String {
+        let mut latex = String::new();
+        for (pos, (tok, pi)) in self.terms().into_iter().enumerate() {
+            let term = latex_term(tok, pi);
+            match (pos, term.strip_prefix('-')) {
+                (0, _) => latex.push_str(&term),
+                (_, Some(negated)) => latex.push_str(&format!(" - {}", negated)),
+                (_, None) => latex.push_str(&format!(" + {}", term)),
+            }
+        }
+        latex
+    }
+}
+
+impl BasicToken {
+    pub fn to_latex(&self) -> String {
+        latex_term(self, false)
+    }
+}
+
+impl Value {
+    /// The value as LaTeX, `None` for values such as polynomials that have no LaTeX form yet.
+    pub fn to_latex(&self) -> Option<String> {
+        match self {
+            Value::Number(token) => Some(token.to_latex()),
+            Value::List(list) => {
+                let elements: Vec<String> = list.iter().map(Token::to_latex).collect();
+                Some(format!("\\left\\{{{}\\right\\}}", elements.join(", ")))
+            }
+            Value::Matrix(matrix) => Some(latex_matrix(matrix)),
+            _ => None,
+        }
+    }
+}
+
+/// Writes `tok`, times π if `pi` is set, with a leading `-` if it is negative.
+fn latex_term(tok: &BasicToken, pi: bool) -> String {
+    let term = match Term::new(tok, pi) {
+        Some(term) => term,
+        None => {
+            let pi = if pi { "\\pi" } else { "" };
+            return match double_parts(tok.double()) {
+                (digits, Some(power)) => format!("{} \\times 10^{{{}}}{}", digits, power, pi),
+                (digits, None) => format!("{}{}", digits, pi),
+            };
+        }
+    };
+    let sign = if term.negative { "-" } else { "" };
+    let mut unit = String::new();
+    if term.pi {
+        unit.push_str("\\pi");
+    }
+    match term.root {
+        Some((2, base)) => unit.push_str(&format!("\\sqrt{{{}}}", base)),
+        Some((index, base)) => unit.push_str(&format!("\\sqrt[{}]{{{}}}", index, base)),
+        None => {}
+    }
+    let num = match (term.numerator(), term.is_rational()) {
+        (Some(1), false) => unit,
+        (Some(num), _) => format!("{}{}", num, unit),
+        // The numerator does not fit, so the whole part is written in front.
+        (None, true) => {
+            return format!("{}{}\\frac{{{}}}{{{}}}", sign, term.int, term.num, term.den)
+        }
+        (None, false) => {
+            return format!(
+                "{}\\left({}\\frac{{{}}}{{{}}}\\right){}",
+                sign, term.int, term.num, term.den, unit
+            )
+        }
+    };
+    match term.den {
+        1 => format!("{}{}", sign, num),
+        den => format!("{}\\frac{{{}}}{{{}}}", sign, num, den),
+    }
+}
+
+fn latex_matrix(matrix: &Matrix) -> String {
+    let (rows, _) = matrix.size();
+    let rows: Vec<String> = (0..rows)
+        .map(|row| {
+            let entries: Vec<String> = matrix.row(row).iter().map(Token::to_latex).collect();
+            entries.join(" & ")
+        })
+        .collect();
+    format!(
+        "\\begin{{pmatrix}} {} \\end{{pmatrix}}",
+        rows.join(" \\\\ ")
+    )
+}
+
+/// Translates LaTeX into the syntax of the calculator, e.g. `\frac{1}{2}x^{3}` into
+/// `(1 / 2) x^3`.
+pub fn translate(input: &str) -> Result<String, EvalError> {
+    let expr = LatexExpression::parse(Rule::latex, input)?.next().unwrap();
+    Ok(translate_pair(expr))
+}
+
+/// Parses and evaluates a single calculation written in LaTeX.
+///
+/// Errors while evaluating have no position since they refer to the translated calculation.
+pub fn calculate_latex(input: &str) -> Result<Token, EvalError> {
+    calculate(&translate(input)?).map_err(EvalError::without_spans)
+}
+
+/// Translates the contents of braces or brackets without adding brackets of its own.
+fn translate_inner(pair: Pair<Rule>) -> String {
+    match pair.as_rule() {
+        Rule::group | Rule::parens => translate_pair(pair.into_inner().next().unwrap()),
+        _ => translate_pair(pair),
+    }
+}
+
+/// Translates an operand of a fraction or a power, in brackets unless it is a single term.
+fn translate_operand(pair: Pair<Rule>) -> String {
+    if pair.as_rule() == Rule::group {
+        let expr = pair.clone().into_inner().next().unwrap();
+        if expr.clone().into_inner().count() == 1 {
+            return translate_pair(expr);
+        }
+    }
+    translate_pair(pair)
+}
+
+fn translate_pair(pair: Pair<Rule>) -> String {
+    match pair.as_rule() {
+        Rule::expr => {
+            let mut text = String::new();
+            let mut after_term = false;
+            for part in pair.into_inner() {
+                match part.as_rule() {
+                    Rule::add => text.push_str(" + "),
+                    Rule::subtract => text.push_str(" - "),
+                    Rule::multiply => text.push_str(" * "),
+                    Rule::divide => text.push_str(" / "),
+                    // Terms next to each other are multiplied, which the calculator also does
+                    // when they are separated by a space.
+                    _ if after_term => text.push(' '),
+                    _ => {}
+                }
+                after_term = part.as_rule() == Rule::term;
+                if after_term {
+                    text.push_str(&translate_pair(part));
+                }
+            }
+            text
+        }
+        Rule::term => {
+            let mut parts = pair.into_inner();
+            let first = parts.next().unwrap();
+            match first.as_rule() {
+                Rule::negative => format!("(-1 * {})", translate_pair(parts.next().unwrap())),
+                _ => translate_pair(first),
+            }
+        }
+        Rule::factor | Rule::bare_factor => pair.into_inner().map(translate_pair).collect(),
+        Rule::superscript => format!("^{}", translate_operand(pair.into_inner().next().unwrap())),
+        Rule::group | Rule::parens => format!("({})", translate_inner(pair)),
+        Rule::frac => {
+            let mut args = pair.into_inner().map(translate_operand);
+            format!("({} / {})", args.next().unwrap(), args.next().unwrap())
+        }
+        Rule::sqrt => {
+            let mut parts: Vec<Pair<Rule>> = pair.into_inner().collect();
+            let arg = translate_inner(parts.pop().unwrap());
+            let index = parts.pop().map(translate_inner);
+            match index.as_deref() {
+                None | Some("2") => format!("sqrt({})", arg),
+                Some("3") => format!("cbrt({})", arg),
+                Some(index) => format!("({})^(1 / ({}))", arg, index),
+            }
+        }
+        Rule::function => {
+            let mut parts = pair.into_inner();
+            let name = parts.next().unwrap().as_str();
+            let mut power = String::new();
+            let mut arg = parts.next().unwrap();
+            if arg.as_rule() == Rule::superscript {
+                power = translate_pair(arg);
+                arg = parts.next().unwrap();
+            }
+            format!("{}({}){}", name, translate_inner(arg), power)
+        }
+        Rule::call => {
+            let mut parts = pair.into_inner();
+            let name = translate_pair(parts.next().unwrap());
+            let args: Vec<String> = parts.map(translate_pair).collect();
+            format!("{}({})", name, args.join("; "))
+        }
+        Rule::bare_argument => {
+            let factors: Vec<String> = pair.into_inner().map(translate_pair).collect();
+            factors.join(" ")
+        }
+        Rule::pi => "pi".to_string(),
+        Rule::greek | Rule::name => pair.into_inner().next().unwrap().as_str().to_string(),
+        _ => pair.as_str().to_string(),
+    }
+}
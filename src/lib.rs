@@ -1,20 +1,54 @@
-#![allow(illegal_floating_point_literal_pattern)]
 #[macro_use]
 extern crate lazy_static;
 #[macro_use]
 extern crate pest_derive;
 #[macro_use]
 pub mod macros;
+pub mod environment;
 pub mod expression;
+pub mod float;
+pub mod integrate;
+pub mod latex;
+pub mod linsolve;
+pub mod list;
+pub mod mathml;
+pub mod matrix;
 pub mod my_math;
+pub mod nsolve;
 pub mod number;
+pub mod polynomial;
+pub mod precision;
+pub mod random;
+pub mod registry;
+pub mod series;
+pub mod solve;
+pub mod stats;
+pub mod steps;
+pub mod symbolic;
 pub mod trig_number;
 pub mod types;
+pub mod value;
 
 #[cfg(test)]
 mod tests {
-    use crate::expression::{eval, Expression, Parser, Rule};
-    use crate::types::{BasicToken, Token};
+    use crate::environment::Environment;
+    use crate::expression::{
+        calculate, eval, evaluate, run, EvalOptions, Expression, Mode, Parser, Rule,
+    };
+    use crate::float::{calculate_float, within_tolerance};
+    use crate::latex::{calculate_latex, translate};
+    use crate::linsolve::LinearSolutions;
+    use crate::matrix::Matrix;
+    use crate::number::{add, compare, div, sub};
+    use crate::polynomial::Polynomial;
+    use crate::precision::{LossReason, Operation};
+    use crate::registry::{Arity, Registry};
+    use crate::solve::{Root, Solutions};
+    use crate::steps::Step;
+    use crate::types::{BasicToken, MathError, Span, Token};
+    use crate::value::Value;
+    use std::cmp::Ordering;
+    use std::sync::Arc;
     use BasicToken::*;
     use Rule::calculation;
     use Token::*;
@@ -23,50 +57,79 @@ mod tests {
     fn number_parse() {
         assert_eq!(
             eval(
-                Expression::parse(calculation, "170141183460469231731687303715884105727").unwrap()
+                Expression::parse(calculation, "170141183460469231731687303715884105727").unwrap(),
+                &Environment::default()
             ),
             Ok(Basic(Integer(i128::MAX)))
         );
         assert_eq!(
             eval(
-                Expression::parse(calculation, "170141183460469231731687303715884105728").unwrap()
+                Expression::parse(calculation, "170141183460469231731687303715884105728").unwrap(),
+                &Environment::default()
             ),
             Ok(Basic(BasicToken::Double(i128::MAX as f64 + 1_f64)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "+5").unwrap()),
+            eval(
+                Expression::parse(calculation, "+5").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(5)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-5").unwrap()),
+            eval(
+                Expression::parse(calculation, "-5").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(-5)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "5.5").unwrap()),
+            eval(
+                Expression::parse(calculation, "5.5").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(5, 1, 2)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-5.5").unwrap()),
+            eval(
+                Expression::parse(calculation, "-5.5").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(-5, -1, 2)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, ".5").unwrap()),
+            eval(
+                Expression::parse(calculation, ".5").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(0, 1, 2)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-5.0").unwrap()),
+            eval(
+                Expression::parse(calculation, "-5.0").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(-5)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "1.0").unwrap()),
+            eval(
+                Expression::parse(calculation, "1.0").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(1)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "0pi").unwrap()),
+            eval(
+                Expression::parse(calculation, "0pi").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(0)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-0.5").unwrap()),
+            eval(
+                Expression::parse(calculation, "-0.5").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(0, -1, 2)))
         );
         assert!(Expression::parse(calculation, "-.5").is_err());
@@ -78,49 +141,75 @@ mod tests {
         assert_eq!(
             eval(
                 Expression::parse(calculation, "170141183460469231731687303715884105727e0")
-                    .unwrap()
+                    .unwrap(),
+                &Environment::default()
             ),
             Ok(Basic(Integer(i128::MAX)))
         );
         assert_eq!(
             eval(
                 Expression::parse(calculation, "170141183460469231731687303715884105728e2")
-                    .unwrap()
+                    .unwrap(),
+                &Environment::default()
             ),
             Ok(Basic(BasicToken::Double(
                 (i128::MAX as f64 + 1_f64) * 100_f64
             )))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "+5e-1").unwrap()),
+            eval(
+                Expression::parse(calculation, "+5e-1").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(0, 1, 2)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-5e-1").unwrap()),
+            eval(
+                Expression::parse(calculation, "-5e-1").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(0, -1, 2)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "5.5e-1").unwrap()),
+            eval(
+                Expression::parse(calculation, "5.5e-1").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(0, 11, 20)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-5.5e+1").unwrap()),
+            eval(
+                Expression::parse(calculation, "-5.5e+1").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(-55)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-595.524e-1").unwrap()),
+            eval(
+                Expression::parse(calculation, "-595.524e-1").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(-59, -1381, 2500)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, ".5e0").unwrap()),
+            eval(
+                Expression::parse(calculation, ".5e0").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(BasicToken::fraction(0, 1, 2)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "-5.0e1").unwrap()),
+            eval(
+                Expression::parse(calculation, "-5.0e1").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(-50)))
         );
         assert_eq!(
-            eval(Expression::parse(calculation, "1.0e2").unwrap()),
+            eval(
+                Expression::parse(calculation, "1.0e2").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(100)))
         );
         assert!(Expression::parse(calculation, "-.5e5").is_err());
@@ -130,8 +219,1307 @@ mod tests {
     #[test]
     fn zero_tests() {
         assert_eq!(
-            eval(Expression::parse(calculation, "-5+5pi+5-5pi").unwrap()),
+            eval(
+                Expression::parse(calculation, "-5+5pi+5-5pi").unwrap(),
+                &Environment::default()
+            ),
             Ok(Basic(Integer(0)))
         );
     }
+
+    #[test]
+    fn tiny_values() {
+        // √(10^30 + 1) - 10^15 is about 5e-16, which is 0.0 as a double but not zero.
+        let tiny = calculate("√1000000000000000000000000000001 - 1000000000000000").unwrap();
+        assert_eq!(tiny.double(), 0.0);
+        assert!(!tiny.is_zero());
+        assert!(calculate("√2 - √2").unwrap().is_zero());
+        assert!(calculate("0.0").unwrap().is_zero());
+        assert_eq!(compare(&tiny, &Basic(Integer(0))), Ok(Ordering::Greater));
+        assert_eq!(compare(&Basic(Integer(0)), &tiny), Ok(Ordering::Less));
+        // 2 - 1/259717522849^2, whose double is 2.0.
+        let square = calculate("(367296043199/259717522849)^2").unwrap();
+        assert_eq!(compare(&square, &Basic(Integer(2))), Ok(Ordering::Less));
+    }
+
+    #[test]
+    fn root_division() {
+        assert_eq!(
+            calculate("1/sqrt(3)"),
+            Ok(Basic(BasicToken::s_frac_root(0, 1, 3, 3)))
+        );
+        assert_eq!(
+            calculate("1/(2sqrt(3))"),
+            Ok(Basic(BasicToken::s_frac_root(0, 1, 6, 3)))
+        );
+        assert_eq!(
+            calculate("(sqrt(2)/2)^3"),
+            Ok(Basic(BasicToken::s_frac_root(0, 1, 4, 2)))
+        );
+        assert_eq!(
+            calculate("cbrt(2)^-1"),
+            Ok(Basic(BasicToken::c_frac_root(0, 1, 2, 4)))
+        );
+    }
+
+    #[test]
+    fn combined_addition() {
+        let value = calculate("1 + sqrt(2) + sqrt(3)").unwrap().double();
+        assert!(within_tolerance(1.0 + 2f64.sqrt() + 3f64.sqrt(), value));
+        assert_eq!(
+            calculate("sqrt(2) + 0"),
+            Ok(Basic(BasicToken::s_int_root(1, 2)))
+        );
+        // Division by a + b√c multiplies by the conjugate.
+        assert_eq!(
+            calculate("1/(1 + sqrt(2)) - (sqrt(2) - 1)"),
+            Ok(Basic(Integer(0)))
+        );
+    }
+
+    #[test]
+    fn combined_multiplication() {
+        let value = calculate("(1 + sqrt(2)) * pi").unwrap();
+        assert!((value.double() - (1.0 + 2f64.sqrt()) * std::f64::consts::PI).abs() < 1e-12);
+        // The first term is multiplied before the second one overflows.
+        let value = calculate("(1 + 10^20 sqrt(2)) * 10^20").unwrap().double();
+        let expected = (1.0 + 1e20 * 2f64.sqrt()) * 1e20;
+        assert!((value - expected).abs() / expected < 1e-12);
+    }
+
+    #[test]
+    fn error_context() {
+        let error = calculate("1/(pi-pi)").unwrap_err();
+        assert_eq!(error.kind, MathError::DivisionByZero);
+        assert_eq!(error.span, Some(Span::new(1, 2)));
+        assert_eq!(error.text, "/");
+        assert_eq!(error.operands, vec![Basic(Integer(1)), Basic(Integer(0))]);
+        assert_eq!(
+            error.highlight("1/(pi-pi)"),
+            Some("1/(pi-pi)\n ^".to_string())
+        );
+
+        let error = calculate("2+sqrt(5/0)").unwrap_err();
+        assert_eq!(error.kind, MathError::DivisionByZero);
+        assert_eq!(error.text, "sqrt(5/0)");
+        assert_eq!(error.root_cause().span, Some(Span::new(8, 9)));
+
+        let error = calculate("5+*").unwrap_err();
+        assert_eq!(error.kind, MathError::SyntaxError);
+        assert_eq!(error.span, Some(Span::new(2, 2)));
+    }
+
+    #[test]
+    fn precision_loss() {
+        let evaluation = evaluate(
+            "2^(1/5) + 1",
+            &mut Environment::default(),
+            &EvalOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(evaluation.warnings.len(), 1);
+        assert_eq!(evaluation.warnings[0].operation, Operation::Power);
+        assert_eq!(
+            evaluation.warnings[0].operands,
+            vec![Basic(Integer(2)), Basic(BasicToken::fraction(0, 1, 5))]
+        );
+        assert_eq!(
+            evaluation.warnings[0].reason,
+            LossReason::UnsupportedRoot(5)
+        );
+
+        let evaluation = evaluate(
+            "sin(pi/7)",
+            &mut Environment::default(),
+            &EvalOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(evaluation.warnings[0].reason, LossReason::NonTableAngle);
+
+        let evaluation = evaluate(
+            "170141183460469231731687303715884105727+1",
+            &mut Environment::default(),
+            &EvalOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(evaluation.warnings[0].operation, Operation::Add);
+        assert_eq!(evaluation.warnings[0].reason, LossReason::Overflow);
+
+        assert!(evaluate(
+            "sqrt(8)/3",
+            &mut Environment::default(),
+            &EvalOptions::default()
+        )
+        .unwrap()
+        .warnings
+        .is_empty());
+    }
+
+    #[test]
+    fn strict_mode() {
+        let strict = EvalOptions {
+            mode: Mode::Strict,
+            ..Default::default()
+        };
+        let error = evaluate("1+2^(1/5)", &mut Environment::default(), &strict).unwrap_err();
+        assert_eq!(
+            error.kind,
+            MathError::Inexact(LossReason::UnsupportedRoot(5))
+        );
+        assert_eq!(error.text, "^");
+
+        let error = evaluate("2*sin(pi/7)", &mut Environment::default(), &strict).unwrap_err();
+        assert_eq!(error.kind, MathError::Inexact(LossReason::NonTableAngle));
+        assert_eq!(error.root_cause().text, "sin(pi/7)");
+
+        let error = evaluate(
+            "170141183460469231731687303715884105728",
+            &mut Environment::default(),
+            &strict,
+        )
+        .unwrap_err();
+        assert_eq!(error.kind, MathError::Inexact(LossReason::Overflow));
+
+        assert_eq!(
+            evaluate("sqrt(8)/3+sin(pi/6)", &mut Environment::default(), &strict)
+                .unwrap()
+                .result,
+            Some(Value::Number(Token::combined(
+                vec![
+                    BasicToken::s_frac_root(0, 2, 3, 2),
+                    BasicToken::fraction(0, 1, 2)
+                ],
+                vec![]
+            )))
+        );
+    }
+
+    #[test]
+    fn float_mode() {
+        for input in [
+            "1/3+1/6",
+            "sqrt(72)-2pi",
+            "sin(pi/12)*cos(pi/5)",
+            "atan(1)+asin(1/2)",
+            "min(2;-3.5e2;sqrt(2))^3",
+            "cbrt(54)/4",
+            "2^(1/5)",
+            "(1+sqrt(2))^4",
+        ] {
+            let exact = calculate(input).unwrap().double();
+            let float = calculate_float(input).unwrap();
+            assert!(
+                within_tolerance(exact, float),
+                "{}: {} != {}",
+                input,
+                exact,
+                float
+            );
+        }
+        assert_eq!(
+            calculate_float("1/(pi-pi)").unwrap_err().kind,
+            MathError::DivisionByZero
+        );
+        assert_eq!(
+            evaluate(
+                "1+1",
+                &mut Environment::default(),
+                &EvalOptions {
+                    mode: Mode::Float,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+            .result,
+            Some(Value::Number(Basic(BasicToken::Double(2.0))))
+        );
+    }
+
+    #[test]
+    fn steps() {
+        let options = EvalOptions {
+            steps: true,
+            ..Default::default()
+        };
+        let steps = |input| {
+            evaluate(input, &mut Environment::default(), &options)
+                .unwrap()
+                .steps
+        };
+        assert_eq!(
+            steps("sqrt(72)"),
+            vec![Step::SimplifyRoot {
+                cube: false,
+                base: 72,
+                outside: 6,
+                inside: 2
+            }]
+        );
+        assert_eq!(
+            steps("1/2+1/3").last(),
+            Some(&Step::Arithmetic {
+                operator: '+',
+                lhs: Basic(BasicToken::fraction(0, 1, 2)),
+                rhs: Basic(BasicToken::fraction(0, 1, 3)),
+                result: Basic(BasicToken::fraction(0, 5, 6)),
+            })
+        );
+        assert_eq!(
+            steps("sin(pi/6)").last(),
+            Some(&Step::TrigValue {
+                function: "sin",
+                argument: Pi(BasicToken::fraction(0, 1, 6)),
+                result: Basic(BasicToken::fraction(0, 1, 2)),
+            })
+        );
+        assert!(matches!(
+            steps("sqrt(8)+sqrt(18)").last(),
+            Some(Step::CombineRadicals { operator: '+', .. })
+        ));
+        assert!(evaluate(
+            "sqrt(72)",
+            &mut Environment::default(),
+            &EvalOptions::default()
+        )
+        .unwrap()
+        .steps
+        .is_empty());
+    }
+
+    #[test]
+    fn variables() {
+        let mut env = Environment::default();
+        assert_eq!(
+            run("a = sqrt(2); a^2", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(2)))))
+        );
+        assert_eq!(
+            env.get("a"),
+            Some(&Value::Number(Basic(BasicToken::s_int_root(1, 2))))
+        );
+        assert_eq!(
+            run("b = 3a", &mut env),
+            Ok(Some(Value::Number(Basic(BasicToken::s_int_root(3, 2)))))
+        );
+        assert_eq!(
+            run("b/a", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(3)))))
+        );
+        assert_eq!(
+            run("pix = 2; 2pix", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(4)))))
+        );
+
+        assert_eq!(
+            run("sin = 1", &mut env).unwrap_err().kind,
+            MathError::ReservedName
+        );
+
+        let float = EvalOptions {
+            mode: Mode::Float,
+            ..Default::default()
+        };
+        let result = evaluate("b^2", &mut env, &float).unwrap().result.unwrap();
+        assert!(within_tolerance(18.0, result.number().unwrap().double()));
+        // Only exact evaluation can keep unknown names as symbols.
+        let error = evaluate("a + c", &mut env, &float).unwrap_err();
+        assert_eq!(error.kind, MathError::UndefinedVariable);
+        assert_eq!(error.span, Some(Span::new(4, 5)));
+    }
+
+    #[test]
+    fn history() {
+        let mut env = Environment::default();
+        let options = EvalOptions::default();
+        assert_eq!(
+            evaluate("ans", &mut env, &options).unwrap_err().kind,
+            MathError::UndefinedAnswer
+        );
+        evaluate("sqrt(2)", &mut env, &options).unwrap();
+        evaluate("1/2", &mut env, &options).unwrap();
+        assert_eq!(
+            evaluate("ans * 4", &mut env, &options).unwrap().result,
+            Some(Value::Number(Basic(Integer(2))))
+        );
+        assert_eq!(
+            evaluate("$1^2 + ans(2)", &mut env, &options)
+                .unwrap()
+                .result,
+            Some(Value::Number(Basic(BasicToken::fraction(2, 1, 2))))
+        );
+        assert_eq!(env.history().len(), 4);
+        assert_eq!(
+            env.answer(1),
+            Some(&Value::Number(Basic(BasicToken::s_int_root(1, 2))))
+        );
+        let error = evaluate("1 + $9", &mut env, &options).unwrap_err();
+        assert_eq!(error.kind, MathError::UndefinedAnswer);
+        assert_eq!(error.span, Some(Span::new(4, 6)));
+        assert_eq!(env.history().len(), 4);
+    }
+
+    #[test]
+    fn user_functions() {
+        let mut env = Environment::default();
+        assert_eq!(run("f(x) = x^2 + sqrt(x)", &mut env), Ok(None));
+        assert_eq!(
+            run("f(2)", &mut env),
+            Ok(Some(Value::Number(Token::combined(
+                vec![Integer(4), BasicToken::s_int_root(1, 2)],
+                vec![]
+            ))))
+        );
+        run("hyp(a; b) = sqrt(a^2 + b^2)", &mut env).unwrap();
+        assert_eq!(
+            run("hyp(3; 4)", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(5)))))
+        );
+        assert_eq!(
+            run("hyp(1; 1)", &mut env),
+            Ok(Some(Value::Number(Basic(BasicToken::s_int_root(1, 2)))))
+        );
+
+        let error = run("hyp(1)", &mut env).unwrap_err();
+        assert_eq!(
+            error.kind,
+            MathError::ArgumentCount {
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(error.span, Some(Span::new(0, 6)));
+
+        run("g(x) = 1/x", &mut env).unwrap();
+        let error = run("2 + g(0)", &mut env).unwrap_err();
+        assert_eq!(error.kind, MathError::DivisionByZero);
+        assert_eq!(error.text, "g(0)");
+        assert_eq!(error.highlight("2 + g(0)").unwrap(), "2 + g(0)\n    ^^^^");
+
+        run("r(x) = r(x) + 1", &mut env).unwrap();
+        let error = run("r(1)", &mut env).unwrap_err();
+        assert_eq!(error.kind, MathError::RecursionLimit);
+        assert_eq!(error.cause, None);
+        assert_eq!(
+            run("u(2)", &mut env).unwrap_err().kind,
+            MathError::UndefinedFunction
+        );
+    }
+
+    #[test]
+    fn registry() {
+        let mut env = Environment::default();
+        let registry = env.registry_mut();
+        registry.register_constant("half", Basic(BasicToken::fraction(0, 1, 2)));
+        registry.register_function("avg", Arity::Fixed(2), |args| {
+            div(add(args[0].clone(), args[1].clone())?, Basic(Integer(2)))
+        });
+        registry.register_function("zero", Arity::Variadic, |_| Err(MathError::DomainError));
+        registry.register_float("zero", |_| Ok(0.0));
+
+        assert_eq!(
+            run("avg(half; sqrt(2))", &mut env),
+            Ok(Some(Value::Number(Token::combined(
+                vec![
+                    BasicToken::fraction(0, 1, 4),
+                    BasicToken::s_frac_root(0, 1, 2, 2)
+                ],
+                vec![]
+            ))))
+        );
+        let error = run("zero(1; 2)", &mut env).unwrap_err();
+        assert_eq!(error.kind, MathError::DomainError);
+        assert_eq!(error.operands, vec![Basic(Integer(1)), Basic(Integer(2))]);
+        let float = EvalOptions {
+            mode: Mode::Float,
+            ..Default::default()
+        };
+        let result = evaluate("zero(1) + avg(1; 2)", &mut env, &float).unwrap();
+        assert_eq!(
+            result.result,
+            Some(Value::Number(Basic(BasicToken::Double(1.5))))
+        );
+        assert_eq!(
+            run("half = 1", &mut env).unwrap_err().kind,
+            MathError::ReservedName
+        );
+
+        // Built-ins are registered the same way, so an empty registry has none of them.
+        let mut env = Environment::with_registry(Arc::new(Registry::empty()));
+        assert_eq!(
+            run("sqrt(4)", &mut env).unwrap_err().kind,
+            MathError::UndefinedFunction
+        );
+        assert_eq!(
+            run("sqrt = 2; sqrt(4)", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(8)))))
+        );
+    }
+
+    #[test]
+    fn polynomials() {
+        let mut env = Environment::default();
+        let mut display = |input| run(input, &mut env).unwrap().unwrap().to_string();
+        assert_eq!(display("(x+1)^3"), "x^3 + 3x^2 + 3x + 1");
+        assert_eq!(display("expand((x - 2)(x + 2))"), "x^2 - 4");
+        assert_eq!(display("simplify(2x + 3x - x)"), "4x");
+        assert_eq!(
+            display("collect((x+y+1)^2; x)"),
+            "x^2 + (2y + 2)x + y^2 + 2y + 1"
+        );
+        assert_eq!(display("f(t) = t^2 + 1; f(a+b)"), "a^2 + 2ab + b^2 + 1");
+
+        // Coefficients stay exact and cancelled terms disappear.
+        let square = run("expand((x+√2)^2)", &mut env).unwrap().unwrap();
+        let expected = Polynomial::symbol("x")
+            .pow(2)
+            .unwrap()
+            .plus(
+                Polynomial::symbol("x")
+                    .times(&Polynomial::constant(Basic(BasicToken::s_int_root(2, 2))))
+                    .unwrap(),
+            )
+            .unwrap()
+            .plus(Polynomial::constant(Basic(Integer(2))))
+            .unwrap();
+        assert_eq!(square, Value::Polynomial(expected));
+        assert_eq!(
+            run("(x+√2)^2 - x^2 - 2√2x", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(2)))))
+        );
+
+        let error = run("1 + sin(x)", &mut env).unwrap_err();
+        assert_eq!(error.kind, MathError::NotANumber);
+        assert_eq!(error.span, Some(Span::new(4, 10)));
+        assert_eq!(
+            run("1/x", &mut env).unwrap_err().kind,
+            MathError::NotPolynomial
+        );
+        assert_eq!(
+            run("collect(x; 2)", &mut env).unwrap_err().kind,
+            MathError::ExpectedSymbol
+        );
+    }
+
+    #[test]
+    fn solve() {
+        let mut env = Environment::default();
+        let mut solve = |input| match run(input, &mut env) {
+            Ok(Some(Value::Solutions(solutions))) => Ok(solutions),
+            Ok(other) => panic!("{:?} is not a solution", other),
+            Err(error) => Err(error.kind),
+        };
+        let root = |value, multiplicity| Root {
+            value,
+            multiplicity,
+        };
+        let solutions = |roots, complex| Solutions {
+            symbol: "x".to_string(),
+            roots,
+            complex,
+            identity: false,
+        };
+
+        let one_plus_root_two = add(Basic(Integer(1)), Basic(BasicToken::s_int_root(1, 2)));
+        let one_minus_root_two = add(Basic(Integer(1)), Basic(BasicToken::s_int_root(-1, 2)));
+        assert_eq!(
+            solve("solve(x^2 - 2x - 1 = 0; x)"),
+            Ok(solutions(
+                vec![
+                    root(one_minus_root_two.unwrap(), 1),
+                    root(one_plus_root_two.unwrap(), 1)
+                ],
+                0
+            ))
+        );
+        assert_eq!(
+            solve("solve(2x/3 = 1/2; x)"),
+            Ok(solutions(
+                vec![root(Basic(BasicToken::fraction(0, 3, 4)), 1)],
+                0
+            ))
+        );
+        assert_eq!(
+            solve("solve((x - 1)^2 (x + 2); x)"),
+            Ok(solutions(
+                vec![root(Basic(Integer(-2)), 1), root(Basic(Integer(1)), 2)],
+                0
+            ))
+        );
+        assert_eq!(solve("solve(x^2 + 1 = 0; x)"), Ok(solutions(vec![], 2)));
+        // (x - 1)^3 = 2
+        assert_eq!(
+            solve("solve(x^3 - 3x^2 + 3x - 3; x)"),
+            Ok(solutions(
+                vec![root(
+                    add(Basic(BasicToken::c_int_root(1, 2)), Basic(Integer(1))).unwrap(),
+                    1
+                )],
+                2
+            ))
+        );
+        // The variable is ignored inside solve.
+        assert_eq!(
+            solve("x = 5; solve(x^3 = 4x; x)"),
+            Ok(solutions(
+                vec![
+                    root(Basic(Integer(-2)), 1),
+                    root(Basic(Integer(0)), 1),
+                    root(Basic(Integer(2)), 1)
+                ],
+                0
+            ))
+        );
+
+        assert!(solve("solve(x + 1 = 1 + x; x)").unwrap().identity);
+        assert_eq!(solve("solve(x = x + 1; x)"), Ok(solutions(vec![], 0)));
+        assert_eq!(solve("solve(x^3 + x + 1; x)"), Err(MathError::NoClosedForm));
+        assert_eq!(solve("solve(cos(x) = x; x)"), Err(MathError::NoClosedForm));
+        assert_eq!(solve("sin(x = 1)"), Err(MathError::UnexpectedEquation));
+    }
+
+    #[test]
+    fn nsolve() {
+        let mut env = Environment::default();
+        let options = EvalOptions::default();
+        let evaluation = evaluate("nsolve(cos(x) = x; x; 1)", &mut env, &options).unwrap();
+        let root = evaluation.result.unwrap().number().unwrap().double();
+        assert!(within_tolerance(0.7390851332151607, root));
+        assert_eq!(evaluation.warnings.len(), 1);
+        assert_eq!(evaluation.warnings[0].reason, LossReason::NoClosedForm);
+        assert!(matches!(
+            evaluation.warnings[0].operation,
+            Operation::RootFinding(convergence) if convergence.error < 1e-9
+        ));
+
+        // Closed forms are preferred, picking the root closest to the start.
+        let evaluation = evaluate("nsolve(x^2 = 2; x; -5)", &mut env, &options).unwrap();
+        assert_eq!(
+            evaluation.result,
+            Some(Value::Number(Basic(BasicToken::s_int_root(-1, 2))))
+        );
+        assert!(evaluation.warnings.is_empty());
+
+        let roots = match run("roots(sin(x); x; 1; 10)", &mut env) {
+            Ok(Some(Value::Solutions(solutions))) => solutions.roots,
+            other => panic!("{:?} are not roots", other),
+        };
+        assert_eq!(roots.len(), 3);
+        for (root, multiple) in roots.iter().zip(1..) {
+            let expected = multiple as f64 * std::f64::consts::PI;
+            assert!(within_tolerance(expected, root.value.double()));
+        }
+        // The pole of tan is not a root.
+        assert_eq!(
+            run("roots(tan(x); x; 1; 2)", &mut env),
+            Ok(Some(Value::Solutions(Solutions {
+                symbol: "x".to_string(),
+                roots: vec![],
+                complex: 0,
+                identity: false,
+            })))
+        );
+
+        assert_eq!(
+            run("nsolve(x^2 + 1; x; 0)", &mut env).unwrap_err().kind,
+            MathError::NoConvergence
+        );
+        let strict = EvalOptions {
+            mode: Mode::Strict,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate("nsolve(cos(x) = x; x; 1)", &mut env, &strict)
+                .unwrap_err()
+                .kind,
+            MathError::Inexact(LossReason::NoClosedForm)
+        );
+        let float = EvalOptions {
+            mode: Mode::Float,
+            ..Default::default()
+        };
+        let result = evaluate("nsolve(2^x = 10; x; 1)", &mut env, &float).unwrap();
+        let root = result.result.unwrap().number().unwrap().double();
+        assert!(within_tolerance(10_f64.log2(), root));
+    }
+
+    #[test]
+    fn diff() {
+        let mut env = Environment::default();
+        let mut display = |input| run(input, &mut env).unwrap().unwrap().to_string();
+        assert_eq!(display("diff(x^3 - 2x + 1; x)"), "3x^2 - 2");
+        assert_eq!(display("diff(sin(x); x)"), "cos(x)");
+        assert_eq!(display("diff(cos(x^2); x)"), "-2x*sin(x^2)");
+        assert_eq!(display("diff(√(x^2 + 1); x)"), "x/√(x^2 + 1)");
+        assert_eq!(display("diff(√x; x)"), "1/(2√x)");
+        assert_eq!(display("diff(cbrt(x); x)"), "1/(3∛x^2)");
+        assert_eq!(display("diff(1/x; x)"), "-1/x^2");
+        assert_eq!(display("diff(sin(x)^2 + cos(x)^2; x)"), "0");
+        assert_eq!(
+            display("f(t) = t^2 sin(t); diff(f(x); x)"),
+            "2x*sin(x) + x^2*cos(x)"
+        );
+
+        // Derivatives at a point are exact.
+        assert_eq!(
+            run("diff(sin(x); x; pi/3)", &mut env),
+            Ok(Some(Value::Number(Basic(BasicToken::fraction(0, 1, 2)))))
+        );
+        assert_eq!(
+            run("diff(tan(x); x; pi/4)", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(2)))))
+        );
+        assert_eq!(
+            run("diff(2^x; x)", &mut env).unwrap_err().kind,
+            MathError::NoDerivative
+        );
+    }
+
+    #[test]
+    fn integrate() {
+        let mut env = Environment::default();
+        let options = EvalOptions::default();
+        assert_eq!(
+            run("integrate(sin(x); x; 0; pi)", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(2)))))
+        );
+        assert_eq!(
+            run("integrate(x*(x + 1); x; 0; 1)", &mut env),
+            Ok(Some(Value::Number(Basic(BasicToken::fraction(0, 5, 6)))))
+        );
+        assert_eq!(
+            run("integrate(√x; x; 0; 4)", &mut env),
+            Ok(Some(Value::Number(Basic(BasicToken::fraction(5, 1, 3)))))
+        );
+        assert_eq!(
+            run("integrate(cos(2x); x; 0; pi/4)", &mut env),
+            Ok(Some(Value::Number(Basic(BasicToken::fraction(0, 1, 2)))))
+        );
+        // The antiderivative has to be defined on the whole interval.
+        assert_eq!(
+            run("integrate(1/x^2; x; 1; 2)", &mut env)
+                .unwrap()
+                .unwrap()
+                .to_string(),
+            "1/2"
+        );
+        assert_eq!(
+            run("integrate(x^(-1/2); x; 0; 1)", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(2)))))
+        );
+        let mut error = |input| run(input, &mut env).unwrap_err().kind;
+        assert_eq!(error("integrate(1/x^2; x; -1; 1)"), MathError::Divergent);
+        assert_eq!(error("integrate(x^(-3); x; -1; 2)"), MathError::Divergent);
+        // Poles without an antiderivative are found by the quadrature.
+        assert_eq!(error("integrate(1/x; x; 0; 1)"), MathError::Divergent);
+        assert_eq!(error("integrate(tan(x); x; 0; 2)"), MathError::Divergent);
+        assert_eq!(
+            error("integrate(sqrt(x); x; -4; 0)"),
+            MathError::ComplexNumber
+        );
+
+        // Without an antiderivative the result is a double with an error estimate.
+        let evaluation = evaluate("integrate(x sin(x); x; 0; pi)", &mut env, &options).unwrap();
+        let value = evaluation.result.unwrap().number().unwrap().double();
+        assert!(within_tolerance(std::f64::consts::PI, value));
+        assert_eq!(evaluation.warnings.len(), 1);
+        assert_eq!(evaluation.warnings[0].reason, LossReason::NoAntiderivative);
+        assert!(matches!(
+            evaluation.warnings[0].operation,
+            Operation::Integration(convergence) if convergence.error < 1e-9
+        ));
+        let evaluation = evaluate("integrate(1/√x; x; 0; 1)", &mut env, &options).unwrap();
+        let value = evaluation.result.unwrap().number().unwrap().double();
+        assert!((value - 2.0).abs() < 1e-8);
+
+        let strict = EvalOptions {
+            mode: Mode::Strict,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate("integrate(1/x; x; 1; 2)", &mut env, &strict)
+                .unwrap_err()
+                .kind,
+            MathError::Inexact(LossReason::NoAntiderivative)
+        );
+        assert_eq!(
+            run("integrate(x = 1; x; 0; 1)", &mut env).unwrap_err().kind,
+            MathError::UnexpectedEquation
+        );
+    }
+
+    #[test]
+    fn series() {
+        let mut env = Environment::default();
+        let mut number = |input| run(input, &mut env).unwrap().unwrap();
+        assert_eq!(
+            number("sum(1/i^2; i; 1; 10)"),
+            Value::Number(Basic(BasicToken::fraction(1, 698249, 1270080)))
+        );
+        assert_eq!(
+            number("prod(i; i; 1; 10)"),
+            Value::Number(Basic(Integer(3628800)))
+        );
+        assert_eq!(number("sum(i; i; 5; 1)"), Value::Number(Basic(Integer(0))));
+        assert_eq!(number("prod(i; i; 5; 1)"), Value::Number(Basic(Integer(1))));
+        assert_eq!(number("sum(x^i; i; 0; 2)").to_string(), "x^2 + x + 1");
+
+        // Long arithmetic and geometric series use their closed forms.
+        assert_eq!(
+            number("sum(2i + 3; i; 0; 10^9)"),
+            Value::Number(Basic(Integer(1_000_000_004_000_000_003)))
+        );
+        assert_eq!(
+            number("sum(3(-1)^i; i; 0; 10^6)"),
+            Value::Number(Basic(Integer(3)))
+        );
+        assert_eq!(
+            number("prod(-1*(-1)^i; i; 1; 3001)"),
+            Value::Number(Basic(Integer(1)))
+        );
+
+        // The index does not change a variable with the same name.
+        assert_eq!(
+            number("i = 7; sum(i; i; 1; 3) + i"),
+            Value::Number(Basic(Integer(13)))
+        );
+        assert_eq!(
+            run("sum(1/i; i; 1; 10^7)", &mut env).unwrap_err().kind,
+            MathError::TooManyTerms
+        );
+        assert_eq!(
+            run("sum(i; i; 1/2; 3)", &mut env).unwrap_err().kind,
+            MathError::ExpectedInteger
+        );
+    }
+
+    #[test]
+    fn matrices() {
+        let mut env = Environment::default();
+        let mut display = |input| run(input, &mut env).unwrap().unwrap().to_string();
+        assert_eq!(
+            display("[[1; 2]; [3; 4]] * [[0; 1]; [1; 0]]"),
+            "[[2; 1]; [4; 3]]"
+        );
+        assert_eq!(display("[[1; 2]; [3; 4]] * [5; 6]"), "[17; 39]");
+        assert_eq!(
+            display("2[[1; 2]; [3; 4]] - [[1; 1]; [1; 1]]"),
+            "[[1; 3]; [5; 7]]"
+        );
+        assert_eq!(
+            display("transpose([[1; 2; 3]; [4; 5; 6]])"),
+            "[[1; 4]; [2; 5]; [3; 6]]"
+        );
+        assert_eq!(
+            display("rref([[1; 2; 3]; [4; 5; 6]])"),
+            "[[1; 0; -1]; [0; 1; 2]]"
+        );
+        assert_eq!(display("rank([[1; 2]; [2; 4]])"), "1");
+        assert_eq!(display("det([[0; 1; 2]; [1; 0; 3]; [4; -3; 8]])"), "-2");
+        assert_eq!(display("[[1; 1]; [1; 0]]^10"), "[[89; 55]; [55; 34]]");
+
+        // Inverses keep fractions and radicals.
+        let sqrt_2 = Basic(BasicToken::s_int_root(1, 2));
+        let inverse = Matrix::new(vec![
+            vec![Basic(Integer(1)), sqrt_2.negate().unwrap()],
+            vec![Basic(Integer(0)), Basic(Integer(1))],
+        ]);
+        assert_eq!(
+            run("inv([[1; √2]; [0; 1]])", &mut env),
+            Ok(Some(Value::Matrix(inverse.unwrap())))
+        );
+        let evaluation = evaluate(
+            "m = [[2; 1/2]; [√3; 1]]; inv(m) m",
+            &mut env,
+            &EvalOptions::default(),
+        )
+        .unwrap();
+        assert!(evaluation.warnings.is_empty());
+        assert_eq!(evaluation.result.unwrap().to_string(), "[[1; 0]; [0; 1]]");
+
+        let mut error = |input| run(input, &mut env).unwrap_err().kind;
+        assert_eq!(
+            error("[1; 2] + [1; 2; 3]"),
+            MathError::SizeMismatch {
+                lhs: (2, 1),
+                rhs: (3, 1)
+            }
+        );
+        assert_eq!(error("[[1; 2]; [3]]"), MathError::RaggedMatrix);
+        assert_eq!(
+            error("det([[1; 2; 3]; [4; 5; 6]])"),
+            MathError::NotSquare { rows: 2, cols: 3 }
+        );
+        assert_eq!(error("inv([[1; 2]; [2; 4]])"), MathError::SingularMatrix);
+        assert_eq!(error("det(3)"), MathError::ExpectedMatrix);
+    }
+
+    #[test]
+    fn statistics() {
+        let mut env = Environment::default();
+        let mut result = |input| run(input, &mut env).unwrap().unwrap().number().unwrap();
+        let fraction = |int, num, den| Basic(BasicToken::fraction(int, num, den));
+        assert_eq!(result("sum(1; 2; 3; 4)"), Basic(Integer(10)));
+        assert_eq!(result("mean(1/2; 1/3; 1/6)"), fraction(0, 1, 3));
+        assert_eq!(result("median(3; 1; 2; 10)"), fraction(2, 1, 2));
+        assert_eq!(result("median(5; 1; 3)"), Basic(Integer(3)));
+        assert_eq!(result("mode(3; 1; 2; 2; 3)"), Basic(Integer(2)));
+        assert_eq!(result("var(1; 2; 3; 4)"), fraction(1, 2, 3));
+        assert_eq!(result("varp(1; 2; 3; 4)"), fraction(1, 1, 4));
+        assert_eq!(
+            result("stdev(1; 2; 3; 4)"),
+            Basic(BasicToken::s_frac_root(0, 1, 3, 15))
+        );
+        assert_eq!(result("stdevp(2; 4; 4; 4; 5; 5; 7; 9)"), Basic(Integer(2)));
+        assert_eq!(result("geomean(1; 2; 4)"), Basic(Integer(2)));
+        assert_eq!(result("geomean(1; 3)"), Basic(BasicToken::s_int_root(1, 3)));
+        assert_eq!(result("quantile(1; 2; 3; 4; 1/3)"), Basic(Integer(2)));
+        assert_eq!(result("quantile(1; 2; 3; 4; 1/4)"), fraction(1, 3, 4));
+        assert_eq!(result("quantile(4; 1; 3; 2; 1)"), Basic(Integer(4)));
+        // Four arguments with a name second are still a series, whose index hides a variable of
+        // the same name as it does in prod.
+        assert_eq!(result("sum(i; i; 1; 4)"), Basic(Integer(10)));
+        assert_eq!(result("y = 5; sum(1; y; 2; 3)"), Basic(Integer(2)));
+        assert_eq!(
+            run("sum(1; y; 2; 7/2)", &mut env).unwrap_err().kind,
+            MathError::ExpectedInteger
+        );
+
+        assert_eq!(
+            run("var(3)", &mut env).unwrap_err().kind,
+            MathError::ArgumentCount {
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            run("geomean(-1; 2)", &mut env).unwrap_err().kind,
+            MathError::DomainError
+        );
+        assert_eq!(
+            run("quantile(1; 2; 3/2)", &mut env).unwrap_err().kind,
+            MathError::DomainError
+        );
+        assert!(within_tolerance(
+            calculate_float("stdev(1; 2; 3; 4)").unwrap(),
+            (5.0f64 / 3.0).sqrt()
+        ));
+    }
+
+    #[test]
+    fn lists() {
+        let mut env = Environment::default();
+        let list = |elements: Vec<Token>| Ok(Some(Value::List(elements)));
+        let ints = |elements: &[i128]| list(elements.iter().map(|i| Basic(Integer(*i))).collect());
+        assert_eq!(run("{1; 2; 3} * 2 + 1", &mut env), ints(&[3, 5, 7]));
+        assert_eq!(run("{1; 2} * {3; 4}", &mut env), ints(&[3, 8]));
+        assert_eq!(run("2^{1; 2; 3}", &mut env), ints(&[2, 4, 8]));
+        assert_eq!(
+            run("{1; 2} / {2; √2}", &mut env),
+            list(vec![
+                Basic(BasicToken::fraction(0, 1, 2)),
+                Basic(BasicToken::s_int_root(1, 2))
+            ])
+        );
+        assert_eq!(run("range(5; 1; -2)", &mut env), ints(&[5, 3, 1]));
+        assert_eq!(
+            run("range(0; 1; 1/2)", &mut env),
+            list(vec![
+                Basic(Integer(0)),
+                Basic(BasicToken::fraction(0, 1, 2)),
+                Basic(Integer(1))
+            ])
+        );
+        // The end is found with exact comparisons.
+        assert_eq!(
+            run("range(0; 3; √2)", &mut env),
+            list(vec![
+                Basic(Integer(0)),
+                Basic(BasicToken::s_int_root(1, 2)),
+                Basic(BasicToken::s_int_root(2, 2))
+            ])
+        );
+        assert_eq!(run("{}", &mut env), ints(&[]));
+
+        run("f(x) = x^2; g(a; b) = a*b", &mut env).unwrap();
+        assert_eq!(run("map(f; range(1; 4))", &mut env), ints(&[1, 4, 9, 16]));
+        assert_eq!(run("map(cube; {1; 2})", &mut env), ints(&[1, 8]));
+        assert_eq!(run("filter(f; {0; 1; 0; 2})", &mut env), ints(&[1, 2]));
+        assert_eq!(
+            run("reduce(g; range(1; 5))", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(120)))))
+        );
+        assert_eq!(
+            run("reduce(g; {}; 7)", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(7)))))
+        );
+        assert_eq!(
+            run("length(range(1; 10; 3))", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(4)))))
+        );
+
+        // Functions taking any number of arguments take the elements of a list.
+        assert_eq!(
+            run("max({1; 5; 3})", &mut env),
+            Ok(Some(Value::Number(Basic(Integer(5)))))
+        );
+        assert_eq!(
+            run("mean(range(1; 10))", &mut env),
+            Ok(Some(Value::Number(Basic(BasicToken::fraction(5, 1, 2)))))
+        );
+
+        let mut error = |input| run(input, &mut env).unwrap_err().kind;
+        assert_eq!(
+            error("{1; 2} + {1; 2; 3}"),
+            MathError::LengthMismatch { lhs: 2, rhs: 3 }
+        );
+        assert_eq!(error("reduce(g; {})"), MathError::EmptyList);
+        assert_eq!(error("mean({})"), MathError::EmptyList);
+        assert_eq!(error("length(3)"), MathError::ExpectedList);
+        assert_eq!(error("map(h; {1})"), MathError::UndefinedFunction);
+        assert_eq!(error("range(1; 2; 0)"), MathError::DomainError);
+        assert_eq!(error("range(1; 10^6)"), MathError::ListTooLong);
+    }
+
+    #[test]
+    fn conditions() {
+        let mut env = Environment::default();
+        // The branch that is not taken would divide by zero.
+        run("f(x) = if(x = 0; 0; 1/x)", &mut env).unwrap();
+        run("g(x) = piecewise(x < 0; -1*x; x <= 10; x^2; 100)", &mut env).unwrap();
+        let mut result = |input| run(input, &mut env).unwrap().unwrap().number().unwrap();
+        assert_eq!(result("f(0)"), Basic(Integer(0)));
+        assert_eq!(result("f(4)"), Basic(BasicToken::fraction(0, 1, 4)));
+        assert_eq!(result("g(-3)"), Basic(Integer(3)));
+        assert_eq!(result("g(10)"), Basic(Integer(100)));
+        assert_eq!(result("g(11)"), Basic(Integer(100)));
+        assert_eq!(result("piecewise(5)"), Basic(Integer(5)));
+        assert_eq!(result("if(1 ≠ 1; 1; 2)"), Basic(Integer(2)));
+
+        // These differ by less than a double can tell apart.
+        assert_eq!(
+            result("if(√2 > 367296043199/259717522849; 1; 2)"),
+            Basic(Integer(1))
+        );
+        assert_eq!(
+            result("max(√2; 367296043199/259717522849)"),
+            Basic(BasicToken::s_int_root(1, 2))
+        );
+        assert_eq!(result("if(√2 + √3 >= √10; 1; 2)"), Basic(Integer(2)));
+
+        // Sums of square roots are compared exactly, even in strict mode.
+        let strict = EvalOptions {
+            mode: Mode::Strict,
+            ..Default::default()
+        };
+        for (input, expected) in [
+            ("if(√2 + √3 + √5 > √30; 1; 2)", Integer(2)),
+            ("if(1 + √2 - √3 - √(7/10) < 0; 1; 2)", Integer(1)),
+            ("median(√2 + √3; √10; 5)", BasicToken::s_int_root(1, 10)),
+        ] {
+            assert_eq!(
+                evaluate(input, &mut env, &strict).unwrap().result,
+                Some(Value::Number(Basic(expected)))
+            );
+        }
+
+        // With π the sign is only known from the double, which strict mode refuses.
+        for input in ["if(√2 + √3 > pi; 1; 2)", "median(√2 + √3; pi; 1)"] {
+            assert_eq!(
+                evaluate(input, &mut env, &strict).unwrap_err().kind,
+                MathError::Inexact(LossReason::UnknownSign)
+            );
+        }
+
+        assert_eq!(
+            run("piecewise(1; 2)", &mut env).unwrap_err().kind,
+            MathError::ArgumentCount {
+                expected: 3,
+                found: 2
+            }
+        );
+        assert_eq!(
+            run("sin(x < 1)", &mut env).unwrap_err().kind,
+            MathError::UnexpectedComparison
+        );
+        assert_eq!(
+            calculate_float("piecewise(-3 > 0; 1/0; 3) + if(2 > 1; 1; 1/0)"),
+            Ok(4.0)
+        );
+    }
+
+    #[test]
+    fn random() {
+        let mut env = Environment::default();
+        env.set_seed(42);
+        let draws = "randint(1; 6); randint(-3; 3); randfrac(12); choice({1/2; √2; 7}); rand()";
+        let first = run(draws, &mut env);
+        // The sequence restarts with the seed, also from a calculation.
+        assert_eq!(run(&format!("seed(42); {}", draws), &mut env), first);
+        env.set_seed(42);
+        assert_eq!(run(draws, &mut env), first);
+        // Calls to user functions continue the sequence of the session.
+        run("f(x) = randint(1; 1000)", &mut env).unwrap();
+        let direct = run("seed(1); randint(1; 1000); randint(1; 1000)", &mut env);
+        assert_eq!(run("seed(1); f(0); f(0)", &mut env), direct);
+        // A preview draws the numbers that come next without using them up.
+        let preview = run("randint(1; 1000); rand()", &mut env.preview());
+        run("seed(5)", &mut env.preview()).unwrap();
+        assert_eq!(run("randint(1; 1000); rand()", &mut env), preview);
+
+        for _ in 0..200 {
+            let mut number = |input| run(input, &mut env).unwrap().unwrap().number().unwrap();
+            match number("randint(-3; 3)") {
+                Basic(Integer(i)) => assert!((-3..=3).contains(&i)),
+                other => panic!("{:?} is not an integer", other),
+            }
+            match number("randfrac(12)") {
+                Basic(Fraction(fraction)) => {
+                    assert_eq!(fraction.int, 0);
+                    assert!(0 < fraction.num && fraction.num < fraction.den && fraction.den <= 12);
+                }
+                other => panic!("{:?} is not a fraction", other),
+            }
+            let choice = number("choice({1/2; √2; 7})");
+            assert!([0.5, 2f64.sqrt(), 7.0].contains(&choice.double()));
+            let unit = number("rand()").double();
+            assert!((0.0..1.0).contains(&unit));
+        }
+
+        // Like a definition, setting the seed has no result.
+        assert_eq!(run("seed(7)", &mut env), Ok(None));
+        let options = EvalOptions::default();
+        let evaluation = evaluate("rand()", &mut env, &options).unwrap();
+        assert_eq!(evaluation.warnings[0].operation, Operation::Random);
+        let strict = EvalOptions {
+            mode: Mode::Strict,
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate("rand()", &mut env, &strict).unwrap_err().kind,
+            MathError::Inexact(LossReason::Random)
+        );
+
+        let mut error = |input| run(input, &mut env).unwrap_err().kind;
+        assert_eq!(error("randint(5; 1)"), MathError::DomainError);
+        assert_eq!(error("randint(1/2; 3)"), MathError::ExpectedInteger);
+        assert_eq!(error("randfrac(1)"), MathError::DomainError);
+        assert_eq!(error("choice({})"), MathError::EmptyList);
+        assert_eq!(error("seed(-1)"), MathError::DomainError);
+    }
+
+    #[test]
+    fn linear_systems() {
+        let mut env = Environment::default();
+        let fraction = |n, d| Value::Number(div(Basic(Integer(n)), Basic(Integer(d))).unwrap());
+        assert_eq!(
+            run("linsolve(2x + 3y = 1; x - y = 5)", &mut env),
+            Ok(Some(Value::LinearSolutions(LinearSolutions {
+                values: vec![
+                    ("x".to_string(), fraction(16, 5)),
+                    ("y".to_string(), fraction(-9, 5)),
+                ],
+                free: vec![],
+                inconsistent: false,
+            })))
+        );
+        // Radicals in the coefficients stay exact, both unknowns are √2 - 1.
+        let sqrt_2_minus_1 = run("√2 - 1", &mut env).unwrap().unwrap().number().unwrap();
+        match run("linsolve(√2 x + y = 1; x - y = 0)", &mut env) {
+            Ok(Some(Value::LinearSolutions(solutions))) => {
+                assert_eq!(solutions.values.len(), 2);
+                for (_, value) in solutions.values {
+                    let value = value.number().unwrap();
+                    let difference = sub(value, sqrt_2_minus_1.clone());
+                    assert_eq!(difference, Ok(Basic(Integer(0))));
+                }
+            }
+            other => panic!("Expected linear solutions, got {:?}", other),
+        }
+
+        // Underdetermined systems are solved for the first unknowns.
+        let (x, y) = (run("3/2 - z/2", &mut env), run("-1/2 - z/2", &mut env));
+        assert_eq!(
+            run("linsolve(x + y + z = 1; x - y = 2)", &mut env),
+            Ok(Some(Value::LinearSolutions(LinearSolutions {
+                values: vec![
+                    ("x".to_string(), x.unwrap().unwrap()),
+                    ("y".to_string(), y.unwrap().unwrap()),
+                ],
+                free: vec!["z".to_string()],
+                inconsistent: false,
+            })))
+        );
+        let mut display = |input| run(input, &mut env).unwrap().unwrap().to_string();
+        assert_eq!(
+            display("linsolve(x + y = 1; 2x + 2y = 2)"),
+            "x = -y + 1 for any y"
+        );
+        assert_eq!(display("linsolve(x + y = 1; 2x + 2y = 3)"), "No solution");
+        assert_eq!(
+            display("linsolve(x + y = 1; x - y = 1; 2x = 2)"),
+            "x = 1, y = 0"
+        );
+
+        assert_eq!(
+            run("linsolve(x*y = 1; x = 2)", &mut env).unwrap_err().kind,
+            MathError::NotLinear
+        );
+        assert_eq!(
+            run("linsolve(sin(x) = y; x = 1)", &mut env)
+                .unwrap_err()
+                .kind,
+            MathError::NotLinear
+        );
+    }
+
+    #[test]
+    fn display() {
+        let mut env = Environment::default();
+        let mut display = |input: &str, mixed: bool| {
+            let value = run(input, &mut env).unwrap().unwrap();
+            match mixed {
+                true => format!("{:#}", value),
+                false => value.to_string(),
+            }
+        };
+        assert_eq!(display("√6/4 - √2/4", false), "√6/4 - √2/4");
+        assert_eq!(display("pi/2", false), "π/2");
+        assert_eq!(display("-3pi/4", false), "-3π/4");
+        assert_eq!(display("11/2", false), "11/2");
+        assert_eq!(display("11/2", true), "5½");
+        assert_eq!(display("-100/7", true), "-14²⁄₇");
+        assert_eq!(display("1/3", true), "1/3");
+        assert_eq!(display("-1/2 + 1", false), "1/2");
+        assert_eq!(display("1/3 - 8/3", true), "-2⅓");
+        assert_eq!(display("{7/4; 2}", true), "{1¾; 2}");
+        assert_eq!(display("5/4 * √2", true), "5√2/4");
+        // Positive terms come first, then rationals before radicals and π.
+        assert_eq!(display("-1 + √2", false), "√2 - 1");
+        assert_eq!(display("pi - √3 + 1/2", false), "1/2 + π - √3");
+        assert_eq!(display("2∛3/5 - 1", false), "2∛3/5 - 1");
+        assert_eq!(display("√2 * pi", false), "π√2");
+        assert_eq!(display("(x + 1/2)^2 - 3y/4", false), "x^2 + x - 3y/4 + 1/4");
+        assert_eq!(display("solve(2x^2 = 1; x)", false), "x = -√2/2, x = √2/2");
+        assert_eq!(Basic(Double(0.25)).to_string(), "0.25");
+        // Very large and very small doubles are written with a power of ten.
+        assert_eq!(Basic(Double(1.5e60)).to_string(), "1.5e60");
+        assert_eq!(Basic(Double(-2.5e-300)).to_string(), "-2.5e-300");
+        assert_eq!(Basic(Double(123456.5)).to_string(), "123456.5");
+    }
+
+    #[test]
+    fn latex() {
+        let mut env = Environment::default();
+        let mut latex = |input| run(input, &mut env).unwrap().unwrap().to_latex();
+        let expected = |latex: &str| Some(latex.to_string());
+        assert_eq!(
+            latex("√6/4 - √2/4"),
+            expected(r"\frac{\sqrt{6}}{4} - \frac{\sqrt{2}}{4}")
+        );
+        assert_eq!(latex("pi/2"), expected(r"\frac{\pi}{2}"));
+        assert_eq!(latex("-11/2"), expected(r"-\frac{11}{2}"));
+        assert_eq!(latex("2∛3/5 - 1"), expected(r"\frac{2\sqrt[3]{3}}{5} - 1"));
+        assert_eq!(latex("3pi√2"), expected(r"3\pi\sqrt{2}"));
+        assert_eq!(
+            latex("{1/2; √3}"),
+            expected(r"\left\{\frac{1}{2}, \sqrt{3}\right\}")
+        );
+        assert_eq!(
+            latex("[[1; 2]; [3; 4]]"),
+            expected(r"\begin{pmatrix} 1 & 2 \\ 3 & 4 \end{pmatrix}")
+        );
+        assert_eq!(latex("(x + 1)^2"), None);
+        assert_eq!(Basic(Double(0.25)).to_latex(), "0.25");
+        assert_eq!(Basic(Double(-1.5e60)).to_latex(), r"-1.5 \times 10^{60}");
+    }
+
+    #[test]
+    fn mathml() {
+        let mut env = Environment::default();
+        let mut mathml = |input| {
+            let mathml = run(input, &mut env).unwrap().unwrap().to_mathml().unwrap();
+            let content = mathml
+                .strip_prefix("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">")
+                .and_then(|mathml| mathml.strip_suffix("</math>"));
+            content.unwrap().to_string()
+        };
+        assert_eq!(
+            mathml("√6/4 - √2/4"),
+            "<mrow><mfrac><msqrt><mn>6</mn></msqrt><mn>4</mn></mfrac><mo>−</mo>\
+             <mfrac><msqrt><mn>2</mn></msqrt><mn>4</mn></mfrac></mrow>"
+        );
+        assert_eq!(mathml("pi/2"), "<mfrac><mi>π</mi><mn>2</mn></mfrac>");
+        assert_eq!(
+            mathml("-11/2"),
+            "<mrow><mo>−</mo><mfrac><mn>11</mn><mn>2</mn></mfrac></mrow>"
+        );
+        assert_eq!(
+            mathml("2∛3 + 1"),
+            "<mrow><mn>1</mn><mo>+</mo>\
+             <mrow><mn>2</mn><mo>&#x2062;</mo><mroot><mn>3</mn><mn>3</mn></mroot></mrow></mrow>"
+        );
+        assert_eq!(
+            mathml("[[1; 2]; [3; 4]]"),
+            "<mrow><mo>(</mo><mtable><mtr><mtd><mn>1</mn></mtd><mtd><mn>2</mn></mtd></mtr>\
+             <mtr><mtd><mn>3</mn></mtd><mtd><mn>4</mn></mtd></mtr></mtable><mo>)</mo></mrow>"
+        );
+        assert_eq!(
+            run("(x + 1)^2", &mut env).unwrap().unwrap().to_mathml(),
+            None
+        );
+        assert_eq!(
+            Basic(Double(-2.5e-300)).to_mathml(),
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mo>−</mo>\
+             <mrow><mn>2.5</mn><mo>×</mo><msup><mn>10</mn><mn>-300</mn></msup></mrow></mrow></math>"
+        );
+    }
+
+    #[test]
+    fn latex_input() {
+        assert_eq!(
+            translate(r"\frac{1}{2}x^{3} - \sqrt[3]{x} \cdot 2").unwrap(),
+            "(1 / 2) x^3 - cbrt(x) * 2"
+        );
+        assert_eq!(translate(r"x^23").unwrap(), "x^2 3");
+        assert_eq!(
+            translate(r"\sin 2x \cos\left(\pi\right)").unwrap(),
+            "sin(2 x) cos(pi)"
+        );
+
+        let fraction = |int, num, den| Basic(BasicToken::fraction(int, num, den));
+        let result = |input| calculate_latex(input).unwrap();
+        assert_eq!(result(r"\frac{1}{2} + \frac13"), fraction(0, 5, 6));
+        assert_eq!(result(r"\sqrt{8}"), Basic(BasicToken::s_int_root(2, 2)));
+        assert_eq!(result(r"\sqrt[3]{54}"), Basic(BasicToken::c_int_root(3, 2)));
+        assert_eq!(result(r"\sin\left(\frac{\pi}{6}\right)"), fraction(0, 1, 2));
+        assert_eq!(result(r"\tan\frac{\pi}{4} + \cos^2 \pi"), Basic(Integer(2)));
+        assert_eq!(result(r"3 \cdot 4 \times 5 \div 6"), Basic(Integer(10)));
+        assert_eq!(result(r"2^{10} - 2^3"), Basic(Integer(1016)));
+        assert_eq!(result(r"-\frac{1}{2} + 1").to_string(), "1/2");
+        assert_eq!(
+            result(r"\operatorname{max}(1, \sqrt{2})"),
+            Basic(BasicToken::s_int_root(1, 2))
+        );
+
+        // Syntax errors point into the LaTeX, the translation is not shown.
+        let error = calculate_latex(r"\frac{1}{2").unwrap_err();
+        assert_eq!(error.kind, MathError::SyntaxError);
+        assert_eq!(error.span, Some(Span::new(10, 10)));
+        // Functions the calculator does not have are not translated.
+        let error = calculate_latex(r"\ln 2").unwrap_err();
+        assert_eq!(
+            (error.kind, error.span),
+            (MathError::SyntaxError, Some(Span::new(1, 1)))
+        );
+        let error = calculate_latex(r"\frac{1}{0}").unwrap_err();
+        assert_eq!((error.kind, error.span), (MathError::DivisionByZero, None));
+
+        let mut env = Environment::default();
+        let options = EvalOptions {
+            latex: true,
+            ..EvalOptions::default()
+        };
+        let evaluation = evaluate(r"\left(\sqrt{2}\right)^{2}", &mut env, &options).unwrap();
+        assert_eq!(evaluation.result, Some(Value::Number(Basic(Integer(2)))));
+    }
 }
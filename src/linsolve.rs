@@ -0,0 +1,106 @@
+//! Solves systems of linear equations exactly, e.g. `linsolve(2x + 3y = 1; x - y = 5)`.
+//!
+//! The coefficients are put in an augmented matrix which is brought to reduced row echelon form
+//! with [`Matrix::reduce`], so the solutions keep their fractions and radicals. Systems with
+//! infinitely many solutions are described by the unknowns that can be chosen freely.
+
+use crate::matrix::Matrix;
+use crate::number::mul;
+use crate::polynomial::Polynomial;
+use crate::types::{BasicToken, MathError, Token};
+use crate::value::Value;
+use core::fmt;
+
+/// The solutions of a system of linear equations.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LinearSolutions {
+    /// Every unknown that is not free with its value, which may depend on the free ones.
+    pub values: Vec<(String, Value)>,
+    /// The unknowns that can take any value.
+    pub free: Vec<String>,
+    /// The equations contradict each other, as in `x + y = 1; x + y = 2`.
+    pub inconsistent: bool,
+}
+
+impl fmt::Display for LinearSolutions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.inconsistent {
+            return write!(f, "No solution");
+        }
+        let free = match self.free.split_last() {
+            None => String::new(),
+            Some((last, [])) => last.clone(),
+            Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        };
+        if self.values.is_empty() {
+            return match free.is_empty() {
+                true => write!(f, "Every value is a solution"),
+                false => write!(f, "Every {} is a solution", free),
+            };
+        }
+        let values: Vec<String> = self
+            .values
+            .iter()
+            .map(|(symbol, value)| format!("{} = {}", symbol, value))
+            .collect();
+        write!(f, "{}", values.join(", "))?;
+        match free.is_empty() {
+            true => Ok(()),
+            false => write!(f, " for any {}", free),
+        }
+    }
+}
+
+#[inline]
+fn int(value: i128) -> Token {
+    Token::Basic(BasicToken::Integer(value))
+}
+
+/// Solves the system where each polynomial equals zero.
+pub fn linsolve(equations: &[Polynomial]) -> Result<LinearSolutions, MathError> {
+    let mut unknowns: Vec<&str> = equations.iter().flat_map(Polynomial::symbols).collect();
+    unknowns.sort_unstable();
+    unknowns.dedup();
+    // Each row holds the coefficients of the unknowns followed by the constant on the right.
+    let mut rows = Vec::with_capacity(equations.len());
+    for equation in equations {
+        let mut row = vec![int(0); unknowns.len() + 1];
+        for (monomial, coefficient) in equation.terms() {
+            match monomial.as_slice() {
+                [] => row[unknowns.len()] = mul(coefficient.clone(), int(-1))?,
+                [(symbol, 1)] => {
+                    let col = unknowns.binary_search(&symbol.as_str()).unwrap();
+                    row[col] = coefficient.clone();
+                }
+                _ => return Err(MathError::NotLinear),
+            }
+        }
+        rows.push(row);
+    }
+    let (reduced, pivots) = Matrix::new(rows)?.reduce()?;
+    if pivots.last() == Some(&unknowns.len()) {
+        return Ok(LinearSolutions {
+            values: vec![],
+            free: vec![],
+            inconsistent: true,
+        });
+    }
+    let free: Vec<usize> = (0..unknowns.len())
+        .filter(|col| !pivots.contains(col))
+        .collect();
+    let mut values = Vec::with_capacity(pivots.len());
+    for (row, col) in pivots.iter().enumerate() {
+        let mut value = Polynomial::constant(reduced.get(row, unknowns.len()).clone());
+        for other in &free {
+            let term = Polynomial::constant(reduced.get(row, *other).clone())
+                .times(&Polynomial::symbol(unknowns[*other]))?;
+            value = value.minus(term)?;
+        }
+        values.push((unknowns[*col].to_string(), Value::from(value)));
+    }
+    Ok(LinearSolutions {
+        values,
+        free: free.iter().map(|col| unknowns[*col].to_string()).collect(),
+        inconsistent: false,
+    })
+}
@@ -0,0 +1,30 @@
+//! Lists of exact numbers such as `{1; 2; 3}`, see [`crate::value::Value::List`].
+//!
+//! Arithmetic on lists works element by element in [`crate::value`], the functions taking a
+//! function as an argument like `map(f; list)` are special forms in [`crate::expression`].
+
+use crate::number::{add, compare};
+use crate::types::{BasicToken, MathError, Token};
+
+/// The most elements [`range`] creates before failing with [`MathError::ListTooLong`].
+pub const MAX_LENGTH: usize = 100_000;
+
+/// The numbers from `from` to `to` in steps of `step`, including `to` if a step lands on it.
+pub fn range(from: Token, to: Token, step: Token) -> Result<Vec<Token>, MathError> {
+    if step.is_zero() {
+        return Err(MathError::DomainError);
+    }
+    // Greater for a step up, so the list ends once a value is greater than `to`.
+    let direction = compare(&step, &Token::Basic(BasicToken::Integer(0)))?;
+    let mut list = vec![];
+    let mut value = from;
+    while compare(&value, &to)? != direction {
+        if list.len() == MAX_LENGTH {
+            return Err(MathError::ListTooLong);
+        }
+        let next = add(value.clone(), step.clone())?;
+        list.push(value);
+        value = next;
+    }
+    Ok(list)
+}
@@ -3,9 +3,9 @@ mod app;
 #[cfg(feature = "gui")]
 use crate::app::CalcApp;
 #[cfg(not(feature = "gui"))]
-use calculator::expression::{eval, Expression, Parser, Rule};
+use calculator::environment::Environment;
 #[cfg(not(feature = "gui"))]
-use calculator::types::MathError;
+use calculator::expression::{evaluate, EvalOptions, Mode};
 #[cfg(feature = "gui")]
 use eframe::NativeOptions;
 #[cfg(not(feature = "gui"))]
@@ -25,23 +25,104 @@ fn main() {
     };
     eframe::run_native(Box::new(app), native_options);
 }
+/// How the CLI writes results.
+#[cfg(not(feature = "gui"))]
+#[derive(PartialEq)]
+enum Output {
+    Plain,
+    /// Fractions as mixed numbers like 5½.
+    Mixed,
+    Latex,
+    MathML,
+}
+
+#[cfg(not(feature = "gui"))]
+impl Output {
+    /// The name of the markup, for the warning when a value cannot be written with it.
+    fn markup(&self) -> Option<&'static str> {
+        match self {
+            Output::Latex => Some("LaTeX"),
+            Output::MathML => Some("MathML"),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(not(feature = "gui"))]
 fn main() {
+    let mut options = EvalOptions::default();
+    let mut output = Output::Plain;
+    let mut env = Environment::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--strict" => options.mode = Mode::Strict,
+            "--float" => options.mode = Mode::Float,
+            "--steps" => options.steps = true,
+            "--mixed" => output = Output::Mixed,
+            "--latex" => output = Output::Latex,
+            "--mathml" => output = Output::MathML,
+            "--latex-input" => options.latex = true,
+            "--seed" => match args.next().and_then(|seed| seed.parse().ok()) {
+                Some(seed) => env.set_seed(seed),
+                None => {
+                    eprintln!("--seed needs a whole number");
+                    return;
+                }
+            },
+            _ => {
+                eprintln!("Unknown argument: {}", arg);
+                return;
+            }
+        }
+    }
     loop {
         let mut string_expression = String::new();
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut string_expression)
             .expect("Failed to read line");
+        if read == 0 {
+            break;
+        }
         let str_expression = string_expression.trim_end_matches(is_newline);
         if str_expression == "stop" || str_expression == "Stop" {
             break;
         }
-        match match Expression::parse(Rule::calculation, &str_expression) {
-            Ok(calc) => eval(calc),
-            Err(_) => Err(MathError::SyntaxError),
-        } {
-            Err(e) => println!("Got Error: {}", e),
-            Ok(t) => println!("Got Result: {:?}", t),
+        match evaluate(str_expression, &mut env, &options) {
+            Err(e) => {
+                println!("Got Error: {}", e);
+                if let Some(highlight) = e.highlight(str_expression) {
+                    println!("{}", highlight);
+                }
+            }
+            Ok(evaluation) => {
+                for step in evaluation.steps {
+                    println!("  {}", step);
+                }
+                match evaluation.result {
+                    Some(result) => {
+                        let markup = match output {
+                            Output::Latex => result.to_latex(),
+                            Output::MathML => result.to_mathml(),
+                            _ => None,
+                        };
+                        let missing = markup.is_none().then(|| output.markup()).flatten();
+                        let result = match (markup, &output) {
+                            (Some(markup), _) => markup,
+                            (None, Output::Mixed) => format!("{:#}", result),
+                            (None, _) => result.to_string(),
+                        };
+                        println!("Got Result ${}: {}", env.history().len(), result);
+                        if let Some(markup) = missing {
+                            println!("Warning: {} is not available for this value", markup);
+                        }
+                    }
+                    None => println!("No result, the definition or seed is stored"),
+                }
+                for warning in evaluation.warnings {
+                    println!("Warning: {}", warning);
+                }
+            }
         };
     }
 }
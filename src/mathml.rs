@@ -0,0 +1,141 @@
+//! Presentation MathML for exact results, so they can be put in a web page without a converter.
+//!
+//! The terms are written in the same order and with the same signs as the [`std::fmt::Display`]
+//! implementations in [`crate::types`].
+
+use crate::matrix::Matrix;
+use crate::types::{double_parts, BasicToken, Term, Token};
+use crate::value::Value;
+
+/// The invisible operator between the factors of a product such as 3π√2.
+const TIMES: &str = "<mo>&#x2062;</mo>";
+
+/// Wraps the markup of a value in the `math` element.
+fn math(content: String) -> String {
+    format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>",
+        content
+    )
+}
+
+impl Token {
+    /// The number as a `math` element, with fractions as `<mfrac>`, roots as `<msqrt>` or
+    /// `<mroot>` and π as `<mi>π</mi>`.
+    pub fn to_mathml(&self) -> String {
+        math(mathml_token(self))
+    }
+}
+
+impl BasicToken {
+    pub fn to_mathml(&self) -> String {
+        math(mathml_token(&Token::Basic(*self)))
+    }
+}
+
+impl Value {
+    /// The value as a `math` element, `None` for values such as polynomials that have no MathML
+    /// form yet.
+    pub fn to_mathml(&self) -> Option<String> {
+        match self {
+            Value::Number(token) => Some(math(mathml_token(token))),
+            Value::List(list) => {
+                let elements: Vec<String> = list.iter().map(mathml_token).collect();
+                Some(math(format!(
+                    "<mrow><mo>{{</mo>{}<mo>}}</mo></mrow>",
+                    elements.join("<mo>,</mo>")
+                )))
+            }
+            Value::Matrix(matrix) => Some(math(mathml_matrix(matrix))),
+            _ => None,
+        }
+    }
+}
+
+/// Joins markup with `separator`, in an `<mrow>` if there is more than one element.
+fn row(elements: Vec<String>, separator: &str) -> String {
+    match elements.len() {
+        1 => elements.concat(),
+        _ => format!("<mrow>{}</mrow>", elements.join(separator)),
+    }
+}
+
+/// The terms of `token` joined by plus and minus signs.
+fn mathml_token(token: &Token) -> String {
+    let mut elements = vec![];
+    for (pos, (tok, pi)) in token.terms().into_iter().enumerate() {
+        let (negative, term) = mathml_term(tok, pi);
+        match (pos, negative) {
+            (0, false) => {}
+            (_, false) => elements.push("<mo>+</mo>".to_string()),
+            (_, true) => elements.push("<mo>−</mo>".to_string()),
+        }
+        elements.push(term);
+    }
+    row(elements, "")
+}
+
+/// Writes `tok`, times π if `pi` is set, without its sign and returns whether it is negative.
+fn mathml_term(tok: &BasicToken, pi: bool) -> (bool, String) {
+    let pi_factor = || "<mi>π</mi>".to_string();
+    let term = match Term::new(tok, pi) {
+        Some(term) => term,
+        None => {
+            let double = tok.double();
+            let mut factors = match double_parts(double.abs()) {
+                (digits, Some(power)) => vec![format!(
+                    "<mrow><mn>{}</mn><mo>×</mo><msup><mn>10</mn><mn>{}</mn></msup></mrow>",
+                    digits, power
+                )],
+                (digits, None) => vec![format!("<mn>{}</mn>", digits)],
+            };
+            if pi {
+                factors.push(pi_factor());
+            }
+            return (double < 0.0, row(factors, TIMES));
+        }
+    };
+    let mut factors = vec![];
+    match term.numerator() {
+        Some(1) if !term.is_rational() => {}
+        Some(num) => factors.push(format!("<mn>{}</mn>", num)),
+        // The numerator does not fit, so the whole part is written in front.
+        None => factors.push(format!(
+            "<mrow><mn>{}</mn><mfrac><mn>{}</mn><mn>{}</mn></mfrac></mrow>",
+            term.int, term.num, term.den
+        )),
+    }
+    if term.pi {
+        factors.push(pi_factor());
+    }
+    match term.root {
+        Some((2, base)) => factors.push(format!("<msqrt><mn>{}</mn></msqrt>", base)),
+        Some((index, base)) => factors.push(format!(
+            "<mroot><mn>{}</mn><mn>{}</mn></mroot>",
+            base, index
+        )),
+        None => {}
+    }
+    let mathml = match (term.numerator(), term.den) {
+        (None, _) | (_, 1) => row(factors, TIMES),
+        (_, den) => format!("<mfrac>{}<mn>{}</mn></mfrac>", row(factors, TIMES), den),
+    };
+    (term.negative, mathml)
+}
+
+fn mathml_matrix(matrix: &Matrix) -> String {
+    let (rows, _) = matrix.size();
+    let rows: Vec<String> = (0..rows)
+        .map(|row| {
+            let entries: Vec<String> = matrix
+                .row(row)
+                .iter()
+                .map(|entry| format!("<mtd>{}</mtd>", mathml_token(entry)))
+                .collect();
+            format!("<mtr>{}</mtr>", entries.concat())
+        })
+        .collect();
+    format!(
+        "<mrow><mo>(</mo><mtable>{}</mtable><mo>)</mo></mrow>",
+        rows.concat()
+    )
+}
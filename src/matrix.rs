@@ -0,0 +1,315 @@
+//! Matrices with exact entries, written `[[1; 2]; [3; 4]]` with one list per row. A single list
+//! of numbers such as `[1; 2; 3]` is a column vector.
+//!
+//! Every operation uses the exact arithmetic of [`crate::number`], so the inverse of a matrix
+//! with fractions and radicals has fractions and radicals as entries.
+
+use crate::number::{add, div, mul, sub};
+use crate::types::{BasicToken, MathError, Token};
+use core::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    /// The entries row by row.
+    entries: Vec<Token>,
+}
+
+#[inline]
+fn int(value: i128) -> Token {
+    Token::Basic(BasicToken::Integer(value))
+}
+
+impl Matrix {
+    /// A matrix with the given rows, which all need the same length.
+    pub fn new(rows: Vec<Vec<Token>>) -> Result<Matrix, MathError> {
+        let cols = rows.first().map_or(0, Vec::len);
+        if cols == 0 || rows.iter().any(|row| row.len() != cols) {
+            return Err(MathError::RaggedMatrix);
+        }
+        Ok(Matrix {
+            rows: rows.len(),
+            cols,
+            entries: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn column(entries: Vec<Token>) -> Matrix {
+        Matrix {
+            rows: entries.len(),
+            cols: 1,
+            entries,
+        }
+    }
+
+    pub fn identity(size: usize) -> Matrix {
+        let entries = (0..size * size)
+            .map(|i| int((i / size == i % size) as i128))
+            .collect();
+        Matrix {
+            rows: size,
+            cols: size,
+            entries,
+        }
+    }
+
+    /// The number of rows and columns.
+    pub fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> &Token {
+        &self.entries[row * self.cols + col]
+    }
+
+    pub fn row(&self, row: usize) -> &[Token] {
+        &self.entries[row * self.cols..(row + 1) * self.cols]
+    }
+
+    /// The entries of a matrix with a single column, or `None` for other matrices.
+    pub fn as_vector(&self) -> Option<&[Token]> {
+        match self.cols {
+            1 => Some(&self.entries),
+            _ => None,
+        }
+    }
+
+    fn mismatch(&self, other: &Matrix) -> MathError {
+        let size = |matrix: &Matrix| (matrix.rows as u32, matrix.cols as u32);
+        MathError::SizeMismatch {
+            lhs: size(self),
+            rhs: size(other),
+        }
+    }
+
+    fn entrywise(
+        &self,
+        other: &Matrix,
+        operation: fn(Token, Token) -> Result<Token, MathError>,
+    ) -> Result<Matrix, MathError> {
+        if self.size() != other.size() {
+            return Err(self.mismatch(other));
+        }
+        let entries = self
+            .entries
+            .iter()
+            .zip(&other.entries)
+            .map(|(lhs, rhs)| operation(lhs.clone(), rhs.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(Matrix { entries, ..*self })
+    }
+
+    pub fn plus(&self, other: &Matrix) -> Result<Matrix, MathError> {
+        self.entrywise(other, add)
+    }
+
+    pub fn minus(&self, other: &Matrix) -> Result<Matrix, MathError> {
+        self.entrywise(other, sub)
+    }
+
+    /// The matrix product, the columns of `self` have to match the rows of `other`.
+    pub fn times(&self, other: &Matrix) -> Result<Matrix, MathError> {
+        if self.cols != other.rows {
+            return Err(self.mismatch(other));
+        }
+        let mut entries = Vec::with_capacity(self.rows * other.cols);
+        for row in 0..self.rows {
+            for col in 0..other.cols {
+                let mut entry = int(0);
+                for k in 0..self.cols {
+                    let term = mul(self.get(row, k).clone(), other.get(k, col).clone())?;
+                    entry = add(entry, term)?;
+                }
+                entries.push(entry);
+            }
+        }
+        Ok(Matrix {
+            rows: self.rows,
+            cols: other.cols,
+            entries,
+        })
+    }
+
+    /// Every entry multiplied by `factor`.
+    pub fn scale(&self, factor: &Token) -> Result<Matrix, MathError> {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| mul(factor.clone(), entry.clone()))
+            .collect::<Result<_, _>>()?;
+        Ok(Matrix { entries, ..*self })
+    }
+
+    /// The matrix multiplied by itself `power` times, negative powers use the inverse.
+    pub fn pow(&self, power: i128) -> Result<Matrix, MathError> {
+        self.check_square()?;
+        let mut base = match power < 0 {
+            true => self.inverse()?,
+            false => self.clone(),
+        };
+        let mut power = power.unsigned_abs();
+        let mut result = Matrix::identity(self.rows);
+        while power > 0 {
+            if power % 2 == 1 {
+                result = result.times(&base)?;
+            }
+            power /= 2;
+            if power > 0 {
+                base = base.times(&base)?;
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let entries = (0..self.cols)
+            .flat_map(|col| (0..self.rows).map(move |row| (row, col)))
+            .map(|(row, col)| self.get(row, col).clone())
+            .collect();
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            entries,
+        }
+    }
+
+    fn check_square(&self) -> Result<(), MathError> {
+        match self.rows == self.cols {
+            true => Ok(()),
+            false => Err(MathError::NotSquare {
+                rows: self.rows as u32,
+                cols: self.cols as u32,
+            }),
+        }
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        for col in 0..self.cols {
+            self.entries.swap(a * self.cols + col, b * self.cols + col);
+        }
+    }
+
+    /// Subtracts `factor` times row `source` from row `target`, starting at column `from`.
+    fn subtract_row(
+        &mut self,
+        target: usize,
+        source: usize,
+        factor: &Token,
+        from: usize,
+    ) -> Result<(), MathError> {
+        for col in from..self.cols {
+            let term = mul(factor.clone(), self.get(source, col).clone())?;
+            let entry = &mut self.entries[target * self.cols + col];
+            *entry = sub(entry.clone(), term)?;
+        }
+        Ok(())
+    }
+
+    /// The reduced row echelon form and the columns of its pivots.
+    pub(crate) fn reduce(&self) -> Result<(Matrix, Vec<usize>), MathError> {
+        let mut matrix = self.clone();
+        let mut pivots = vec![];
+        for col in 0..matrix.cols {
+            let row = pivots.len();
+            let pivot = match (row..matrix.rows).find(|r| !matrix.get(*r, col).is_zero()) {
+                Some(pivot) => pivot,
+                None => continue,
+            };
+            matrix.swap_rows(row, pivot);
+            let pivot = matrix.get(row, col).clone();
+            // The pivot divided by itself is set directly, the division can need rounding.
+            matrix.entries[row * matrix.cols + col] = int(1);
+            for c in col + 1..matrix.cols {
+                let entry = &mut matrix.entries[row * matrix.cols + c];
+                *entry = div(entry.clone(), pivot.clone())?;
+            }
+            for other in (0..matrix.rows).filter(|other| *other != row) {
+                let factor = matrix.get(other, col).clone();
+                if !factor.is_zero() {
+                    matrix.subtract_row(other, row, &factor, col)?;
+                }
+            }
+            pivots.push(col);
+            if pivots.len() == matrix.rows {
+                break;
+            }
+        }
+        Ok((matrix, pivots))
+    }
+
+    /// The reduced row echelon form.
+    pub fn rref(&self) -> Result<Matrix, MathError> {
+        Ok(self.reduce()?.0)
+    }
+
+    pub fn rank(&self) -> Result<usize, MathError> {
+        Ok(self.reduce()?.1.len())
+    }
+
+    /// The determinant, using Gaussian elimination.
+    pub fn det(&self) -> Result<Token, MathError> {
+        self.check_square()?;
+        let mut matrix = self.clone();
+        let mut det = int(1);
+        for col in 0..matrix.cols {
+            let pivot = match (col..matrix.rows).find(|r| !matrix.get(*r, col).is_zero()) {
+                Some(pivot) => pivot,
+                None => return Ok(int(0)),
+            };
+            if pivot != col {
+                matrix.swap_rows(col, pivot);
+                det = det.negate()?;
+            }
+            let pivot = matrix.get(col, col).clone();
+            det = mul(det, pivot.clone())?;
+            for row in col + 1..matrix.rows {
+                let factor = div(matrix.get(row, col).clone(), pivot.clone())?;
+                if !factor.is_zero() {
+                    matrix.subtract_row(row, col, &factor, col)?;
+                }
+            }
+        }
+        Ok(det)
+    }
+
+    /// The inverse, found by reducing the matrix next to the identity.
+    pub fn inverse(&self) -> Result<Matrix, MathError> {
+        self.check_square()?;
+        let size = self.rows;
+        let identity = Matrix::identity(size);
+        let rows = (0..size)
+            .map(|row| [self.row(row), identity.row(row)].concat())
+            .collect();
+        let (reduced, pivots) = Matrix::new(rows)?.reduce()?;
+        if pivots.len() < size || pivots[size - 1] >= size {
+            return Err(MathError::SingularMatrix);
+        }
+        let rows = (0..size)
+            .map(|row| reduced.row(row)[size..].to_vec())
+            .collect();
+        Matrix::new(rows)
+    }
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format_row = |row: &[Token]| {
+            let entries: Vec<String> = row
+                .iter()
+                .map(|entry| match f.alternate() {
+                    true => format!("{:#}", entry),
+                    false => entry.to_string(),
+                })
+                .collect();
+            format!("[{}]", entries.join("; "))
+        };
+        if let Some(vector) = self.as_vector() {
+            return write!(f, "{}", format_row(vector));
+        }
+        let rows: Vec<String> = (0..self.rows)
+            .map(|row| format_row(self.row(row)))
+            .collect();
+        write!(f, "[{}]", rows.join("; "))
+    }
+}
@@ -1,3 +1,4 @@
+use crate::steps::{self, Step};
 use std::cmp::min;
 
 const MAX_I128_LOG_10: i128 = 38;
@@ -96,6 +97,14 @@ pub fn factorise(base: i128, sqrt: bool) -> FactoriseResult {
         }
         ii += 1;
     }
+    if outside_root != 1 {
+        steps::record(|| Step::SimplifyRoot {
+            cube: !sqrt,
+            base,
+            outside: outside_root,
+            inside: inside_root,
+        });
+    }
     FactoriseResult {
         outside: outside_root,
         inside: inside_root,
@@ -0,0 +1,190 @@
+//! Finds roots of equations numerically, for when [`crate::solve`] has no closed form.
+//!
+//! A sign change around the starting point is searched for first, the root is then refined with
+//! Newton's method, falling back to bisection whenever a Newton step would leave the bracket.
+//! Equations without a sign change near the start, such as double roots, use plain Newton.
+
+use crate::precision::Convergence;
+use crate::types::{EvalError, MathError};
+
+/// Iteration stops once a step is smaller than this relative to the root.
+pub const TOLERANCE: f64 = 1e-12;
+/// Iterations before giving up with [`MathError::NoConvergence`].
+pub const MAX_ITERATIONS: u32 = 200;
+/// The number of pieces the interval of [`roots`] is split into to look for sign changes.
+const SAMPLES: usize = 1000;
+/// How many times the search for a sign change doubles its distance from the start.
+const BRACKET_STEPS: i32 = 40;
+
+#[inline]
+fn converged(step: f64, x: f64) -> bool {
+    step <= TOLERANCE * x.abs().max(1.0)
+}
+
+/// A value of `f` that can be used, points where it cannot be evaluated are skipped.
+fn sample(f: &mut impl FnMut(f64) -> Result<f64, EvalError>, x: f64) -> Option<f64> {
+    f(x).ok().filter(|value| value.is_finite())
+}
+
+/// The derivative of `f` at `x` using a central difference.
+fn slope(f: &mut impl FnMut(f64) -> Result<f64, EvalError>, x: f64) -> Option<f64> {
+    let h = 1e-7 * x.abs().max(1.0);
+    let derivative = (sample(f, x + h)? - sample(f, x - h)?) / (2.0 * h);
+    match derivative.is_finite() && derivative != 0.0 {
+        true => Some(derivative),
+        false => None,
+    }
+}
+
+/// Finds the root in `[lo, hi]` where `f(lo)` and `f(hi)` have different signs.
+fn refine(
+    f: &mut impl FnMut(f64) -> Result<f64, EvalError>,
+    (mut lo, mut hi): (f64, f64),
+    start: f64,
+) -> Result<(f64, Convergence), EvalError> {
+    let lo_negative = sample(f, lo).ok_or(MathError::NoConvergence)? < 0.0;
+    let mut x = match start > lo && start < hi {
+        true => start,
+        false => (lo + hi) / 2.0,
+    };
+    for iterations in 1..=MAX_ITERATIONS {
+        let value = sample(f, x).ok_or(MathError::NoConvergence)?;
+        if value == 0.0 {
+            let error = 0.0;
+            return Ok((x, Convergence { iterations, error }));
+        }
+        match (value < 0.0) == lo_negative {
+            true => lo = x,
+            false => hi = x,
+        }
+        let next = slope(f, x)
+            .map(|derivative| x - value / derivative)
+            .filter(|next| *next > lo && *next < hi)
+            .unwrap_or((lo + hi) / 2.0);
+        let error = (next - x).abs().min(hi - lo);
+        x = next;
+        if converged(error, x) {
+            return Ok((x, Convergence { iterations, error }));
+        }
+    }
+    Err(MathError::NoConvergence.into())
+}
+
+/// Newton's method without a bracket.
+fn newton(
+    f: &mut impl FnMut(f64) -> Result<f64, EvalError>,
+    mut x: f64,
+) -> Result<(f64, Convergence), EvalError> {
+    for iterations in 1..=MAX_ITERATIONS {
+        let value = f(x)?;
+        if value == 0.0 {
+            let error = 0.0;
+            return Ok((x, Convergence { iterations, error }));
+        }
+        let derivative = slope(f, x).ok_or(MathError::NoConvergence)?;
+        let next = x - value / derivative;
+        if !next.is_finite() {
+            break;
+        }
+        let error = (next - x).abs();
+        x = next;
+        if converged(error, x) {
+            return Ok((x, Convergence { iterations, error }));
+        }
+    }
+    Err(MathError::NoConvergence.into())
+}
+
+/// Finds a root of `f` near `start`.
+pub fn nsolve(
+    mut f: impl FnMut(f64) -> Result<f64, EvalError>,
+    start: f64,
+) -> Result<(f64, Convergence), EvalError> {
+    let value = f(start)?;
+    if value == 0.0 {
+        let error = 0.0;
+        return Ok((
+            start,
+            Convergence {
+                iterations: 0,
+                error,
+            },
+        ));
+    }
+    // Walk away from the start in both directions until the sign changes.
+    let (mut left, mut right) = ((start, value), (start, value));
+    for step in 0..BRACKET_STEPS {
+        let distance = 0.01 * 2_f64.powi(step) * start.abs().max(1.0);
+        for (previous, x) in [
+            (&mut left, start - distance),
+            (&mut right, start + distance),
+        ] {
+            if let Some(value) = sample(&mut f, x) {
+                if value == 0.0 {
+                    let error = 0.0;
+                    return Ok((
+                        x,
+                        Convergence {
+                            iterations: 0,
+                            error,
+                        },
+                    ));
+                }
+                if (value < 0.0) != (previous.1 < 0.0) {
+                    let bracket = (previous.0.min(x), previous.0.max(x));
+                    return refine(&mut f, bracket, start);
+                }
+                *previous = (x, value);
+            }
+        }
+    }
+    newton(&mut f, start)
+}
+
+/// Finds every root of `f` between `a` and `b` where its sign changes, in ascending order.
+///
+/// The iterations of every root are added up and the largest error is reported.
+pub fn roots(
+    mut f: impl FnMut(f64) -> Result<f64, EvalError>,
+    a: f64,
+    b: f64,
+) -> Result<(Vec<f64>, Convergence), EvalError> {
+    let (a, b) = (a.min(b), a.max(b));
+    let mut convergence = Convergence {
+        iterations: 0,
+        error: 0.0,
+    };
+    let mut roots: Vec<f64> = vec![];
+    let mut previous: Option<(f64, f64)> = None;
+    for i in 0..=SAMPLES {
+        let x = a + (b - a) * i as f64 / SAMPLES as f64;
+        let value = match sample(&mut f, x) {
+            Some(value) => value,
+            None => continue,
+        };
+        let root = match previous {
+            _ if value == 0.0 => Some(x),
+            Some((lo, lo_value)) if lo_value != 0.0 && (lo_value < 0.0) != (value < 0.0) => {
+                let (root, found) = refine(&mut f, (lo, x), (lo + x) / 2.0)?;
+                convergence.iterations += found.iterations;
+                convergence.error = convergence.error.max(found.error);
+                // The sign also changes at poles such as the one of tan(x) at π/2.
+                let bound = lo_value.abs().max(value.abs());
+                sample(&mut f, root)
+                    .filter(|residual| residual.abs() <= bound)
+                    .map(|_| root)
+            }
+            _ => None,
+        };
+        if let Some(root) = root {
+            if roots
+                .last()
+                .is_none_or(|last| !converged((root - last).abs(), root))
+            {
+                roots.push(root);
+            }
+        }
+        previous = Some((x, value));
+    }
+    Ok((roots, convergence))
+}
@@ -1,5 +1,9 @@
 use crate::my_math::factorise;
-use crate::types::{BasicToken, Combined, Fraction, MathError, Set, Token};
+use crate::precision::{inexact, record, track, LossReason, Operation};
+use crate::steps;
+use crate::types::{BasicToken, CRoot, Combined, Fraction, MathError, SRoot, Set, Token};
+use gcd::Gcd;
+use std::cmp::Ordering;
 
 pub fn try_add(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
     match (lhs, rhs) {
@@ -41,19 +45,31 @@ pub fn try_add(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError
 }
 
 pub fn add(l_number: Token, r_number: Token) -> Result<Token, MathError> {
+    steps::operation('+', l_number, r_number, add_tokens)
+}
+
+fn add_tokens(l_number: Token, r_number: Token) -> Result<Token, MathError> {
     match (l_number, r_number) {
+        // Adding zero to a root would otherwise combine them.
+        commutative!(Token::Basic(BasicToken::Integer(0)), other) => Ok(other),
         (Token::Pi(l), Token::Pi(r)) => match try_add(l, r) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                (l.double() + r.double()) * std::f64::consts::PI
-            )))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Add,
+                vec![Token::Pi(l), Token::Pi(r)],
+                LossReason::Overflow,
+                (l.double() + r.double()) * std::f64::consts::PI,
+            ),
             Err(MathError::Combine) => Ok(Token::combined(vec![], vec![l, r])),
             Ok(BasicToken::Integer(0)) => Ok(Token::Basic(BasicToken::Integer(0))),
             value => Ok(Token::Pi(value?)),
         },
         (Token::Basic(l), Token::Basic(r)) => match try_add(l, r) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                l.double() + r.double()
-            )))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Add,
+                vec![Token::Basic(l), Token::Basic(r)],
+                LossReason::Overflow,
+                l.double() + r.double(),
+            ),
             Err(MathError::Combine) => Ok(Token::combined(vec![l, r], vec![])),
             value => Ok(Token::Basic(value?)),
         },
@@ -115,19 +131,31 @@ fn try_sub(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
 }
 
 pub fn sub(l_number: Token, r_number: Token) -> Result<Token, MathError> {
+    steps::operation('-', l_number, r_number, sub_tokens)
+}
+
+fn sub_tokens(l_number: Token, r_number: Token) -> Result<Token, MathError> {
     match (l_number, r_number) {
+        (l_number, Token::Basic(BasicToken::Integer(0))) => Ok(l_number),
+        (Token::Basic(BasicToken::Integer(0)), r_number) => r_number.negate(),
         (Token::Pi(l), Token::Pi(r)) => match try_sub(l, r) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                (l.double() - r.double()) * std::f64::consts::PI
-            )))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Subtract,
+                vec![Token::Pi(l), Token::Pi(r)],
+                LossReason::Overflow,
+                (l.double() - r.double()) * std::f64::consts::PI,
+            ),
             Err(MathError::Combine) => Ok(Token::combined(vec![], vec![l, r.negate()?])),
             Ok(BasicToken::Integer(0)) => Ok(Token::Basic(BasicToken::Integer(0))),
             value => Ok(Token::Pi(value?)),
         },
         (Token::Basic(l), Token::Basic(r)) => match try_sub(l, r) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                l.double() - r.double()
-            )))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Subtract,
+                vec![Token::Basic(l), Token::Basic(r)],
+                LossReason::Overflow,
+                l.double() - r.double(),
+            ),
             Err(MathError::Combine) => Ok(Token::combined(vec![l, r.negate()?], vec![])),
             value => Ok(Token::Basic(value?)),
         },
@@ -238,27 +266,79 @@ pub fn try_mul(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError
 }
 
 pub fn mul(l_number: Token, r_number: Token) -> Result<Token, MathError> {
+    steps::operation('×', l_number, r_number, mul_tokens)
+}
+
+fn mul_tokens(l_number: Token, r_number: Token) -> Result<Token, MathError> {
     match (l_number, r_number) {
         commutative!(Token::Pi(l), Token::Basic(r)) => match try_mul(l, r) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                (l.double() * r.double()) * std::f64::consts::PI
-            )))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Multiply,
+                vec![Token::Pi(l), Token::Basic(r)],
+                LossReason::Overflow,
+                (l.double() * r.double()) * std::f64::consts::PI,
+            ),
             Ok(BasicToken::Integer(0)) => Ok(Token::Basic(BasicToken::Integer(0))),
             value => Ok(Token::Pi(value?)),
         },
         (Token::Basic(l), Token::Basic(r)) => match try_mul(l, r) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                l.double() * r.double()
-            )))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Multiply,
+                vec![Token::Basic(l), Token::Basic(r)],
+                LossReason::Overflow,
+                l.double() * r.double(),
+            ),
             value => Ok(Token::Basic(value?)),
         },
-        (Token::Pi(l), Token::Pi(r)) => Ok(Token::Basic(BasicToken::Double(double_check!(
-            l.double() * r.double() * std::f64::consts::PI * std::f64::consts::PI
-        )))),
+        (Token::Pi(l), Token::Pi(r)) => inexact(
+            Operation::Multiply,
+            vec![Token::Pi(l), Token::Pi(r)],
+            LossReason::NotRepresentable,
+            l.double() * r.double() * std::f64::consts::PI * std::f64::consts::PI,
+        ),
         commutative!(Token::Combined(l), r) => Ok(l.mul_combined(r)?),
     }
 }
 
+/// The conjugate `a - b√c` of a number `a + b√c` with a rational `a`, and the rational product
+/// `a² - b²c` of the two.
+fn conjugate(number: &Combined) -> Option<(Token, BasicToken)> {
+    if !number.pi.vec.is_empty() {
+        return None;
+    }
+    match number.basic.vec.as_slice() {
+        combined!(
+            rational @ (BasicToken::Integer(_) | BasicToken::Fraction(_)),
+            root @ (BasicToken::SIntRoot(_) | BasicToken::SFracRoot(_))
+        ) => {
+            let conjugate = Token::combined(vec![*rational, root.negate().ok()?], vec![]);
+            let product = try_sub(square(*rational).ok()?, square(*root).ok()?);
+            Some((conjugate, product.ok()?))
+        }
+        _ => None,
+    }
+}
+
+/// `1/root` with the root moved into the numerator, e.g. `1/(2√3)` is `√3/6`.
+fn reciprocal(root: BasicToken) -> Result<BasicToken, MathError> {
+    let (num, den, base) = match root {
+        BasicToken::SIntRoot(r) => (r.mul, 1, r.base),
+        BasicToken::CIntRoot(r) => (r.mul, 1, r.base),
+        BasicToken::SFracRoot(SRoot { mul, base }) | BasicToken::CFracRoot(CRoot { mul, base }) => {
+            (add!(mul!(mul.int, mul.den), mul.num), mul.den, base)
+        }
+        _ => unreachable!(),
+    };
+    let outside = Fraction::new(0, den, mul!(num, base)).normalise()?;
+    match root {
+        BasicToken::SIntRoot(_) | BasicToken::SFracRoot(_) => BasicToken::new_s_root(outside, base),
+        _ => {
+            let root = BasicToken::c_int_root(1, base);
+            try_mul(outside, try_mul(root, root)?)
+        }
+    }
+}
+
 fn try_div(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
     {
         match (lhs, rhs) {
@@ -269,6 +349,15 @@ fn try_div(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
             // 0/a = 0
             (BasicToken::Integer(0), _) => Ok(BasicToken::Integer(0)),
 
+            // Dividing by a root multiplies by its rationalised reciprocal.
+            (
+                lhs,
+                rhs @ (BasicToken::SIntRoot(_)
+                | BasicToken::SFracRoot(_)
+                | BasicToken::CIntRoot(_)
+                | BasicToken::CFracRoot(_)),
+            ) => try_mul(lhs, reciprocal(rhs)?),
+
             (BasicToken::Integer(la), BasicToken::Integer(ra)) => {
                 Fraction::new(0, la, ra).normalise()
             }
@@ -289,22 +378,12 @@ fn try_div(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
             (BasicToken::SIntRoot(la), BasicToken::Integer(ra)) => {
                 BasicToken::new_s_root(Fraction::new(0, la.mul, ra).normalise()?, la.base)
             }
-            (BasicToken::Integer(la), BasicToken::SIntRoot(ra)) => {
-                BasicToken::new_s_root(Fraction::new(0, la, ra.mul).normalise()?, ra.base)
-            }
             (BasicToken::SFracRoot(mut la), BasicToken::Integer(ra)) => {
                 la.mul.num = add!(la.mul.num, mul!(la.mul.int, la.mul.den));
                 la.mul.den = mul!(la.mul.den, ra);
                 la.mul.int = 0;
                 BasicToken::new_s_root(la.mul.normalise()?, la.base)
             }
-            (BasicToken::Integer(la), BasicToken::SFracRoot(mut ra)) => {
-                let old_num = ra.mul.num;
-                ra.mul.num = mul!(la, ra.mul.den);
-                ra.mul.den = add!(old_num, mul!(ra.mul.den, ra.mul.int));
-                ra.mul.int = 0;
-                BasicToken::new_s_root(ra.mul.normalise()?, ra.base)
-            }
             (BasicToken::SIntRoot(la), BasicToken::Fraction(mut ra)) => {
                 let old_num = ra.num;
                 ra.num = mul!(la.mul, ra.den);
@@ -312,82 +391,18 @@ fn try_div(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
                 ra.int = 0;
                 BasicToken::new_s_root(ra.normalise()?, la.base)
             }
-            (BasicToken::Fraction(mut la), BasicToken::SIntRoot(ra)) => {
-                la.num = add!(la.num, mul!(la.int, la.den));
-                la.den = mul!(la.den, ra.mul);
-                la.int = 0;
-                BasicToken::new_s_root(la.normalise()?, ra.base)
-            }
             (BasicToken::SFracRoot(la), BasicToken::Fraction(ra)) => {
                 BasicToken::new_s_root((la.mul / ra)?, la.base)
             }
-            (BasicToken::Fraction(la), BasicToken::SFracRoot(ra)) => {
-                BasicToken::new_s_root((la / ra.mul)?, ra.base)
-            }
-            (BasicToken::SIntRoot(la), BasicToken::SIntRoot(ra)) => {
-                let mut frac = Fraction::new(0, la.mul, mul!(ra.mul, ra.base));
-                let res = factorise(mul!(la.base, ra.base), true);
-                frac.num = mul!(res.outside, frac.num);
-                BasicToken::new_s_root(frac.normalise()?, res.inside)
-            }
-            (BasicToken::SFracRoot(mut la), BasicToken::SIntRoot(ra)) => {
-                la.mul.num = add!(la.mul.num, mul!(la.mul.int, la.mul.den));
-                la.mul.den = mul!(mul!(la.mul.den, ra.mul), ra.base);
-                la.mul.int = 0;
-                let res = factorise(mul!(la.base, ra.base), true);
-                la.mul.num = mul!(la.mul.num, res.outside);
-                BasicToken::new_s_root(la.mul.normalise()?, res.inside)
-            }
-            (BasicToken::SIntRoot(la), BasicToken::SFracRoot(ra)) => {
-                let mut frac = Fraction::new(
-                    0,
-                    mul!(la.mul, ra.mul.den),
-                    mul!(add!(ra.mul.num, mul!(ra.mul.den, ra.mul.int)), ra.base),
-                );
-                let res = factorise(mul!(la.base, ra.base), true);
-                frac.num = mul!(frac.num, res.outside);
-                BasicToken::new_s_root(frac.normalise()?, res.inside)
-            }
-            (BasicToken::SFracRoot(mut la), BasicToken::SFracRoot(mut ra)) => {
-                ra.mul.num = mul!(ra.mul.num, ra.base);
-                ra.mul.int = mul!(ra.mul.int, ra.base);
-                match ra.mul.normalise()? {
-                    BasicToken::Integer(int) => {
-                        la.mul.num = add!(la.mul.num, mul!(la.mul.int, la.mul.den));
-                        la.mul.den = mul!(la.mul.den, int); // Already have multiplied by base so no need to do it again
-                        la.mul.int = 0;
-                        let res = factorise(mul!(la.base, ra.base), true);
-                        la.mul.num = mul!(la.mul.num, res.outside);
-                        BasicToken::new_s_root(la.mul.normalise()?, res.inside)
-                    }
-                    BasicToken::Fraction(frac) => {
-                        let res = factorise(mul!(la.base, ra.base), true);
-                        la.mul.int = mul!(la.mul.int, res.outside);
-                        la.mul.num = mul!(la.mul.int, res.outside);
-                        BasicToken::new_s_root((la.mul / frac)?, res.inside)
-                    }
-                    _ => unreachable!(),
-                }
-            }
             (BasicToken::CIntRoot(la), BasicToken::Integer(ra)) => {
                 BasicToken::new_c_root(Fraction::new(0, la.mul, ra).normalise()?, la.base)
             }
-            (BasicToken::Integer(la), BasicToken::CIntRoot(ra)) => {
-                BasicToken::new_c_root(Fraction::new(0, la, ra.mul).normalise()?, ra.base)
-            }
             (BasicToken::CFracRoot(mut la), BasicToken::Integer(ra)) => {
                 la.mul.num = add!(la.mul.num, mul!(la.mul.int, la.mul.den));
                 la.mul.den = mul!(la.mul.den, ra);
                 la.mul.int = 0;
                 BasicToken::new_c_root(la.mul.normalise()?, la.base)
             }
-            (BasicToken::Integer(la), BasicToken::CFracRoot(mut ra)) => {
-                let old_num = ra.mul.num;
-                ra.mul.num = mul!(la, ra.mul.den);
-                ra.mul.den = add!(old_num, mul!(ra.mul.den, ra.mul.int));
-                ra.mul.int = 0;
-                BasicToken::new_c_root(ra.mul.normalise()?, ra.base)
-            }
             (BasicToken::CIntRoot(la), BasicToken::Fraction(mut ra)) => {
                 let old_num = ra.num;
                 ra.num = mul!(la.mul, ra.den);
@@ -395,106 +410,90 @@ fn try_div(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
                 ra.int = 0;
                 BasicToken::new_c_root(ra.normalise()?, la.base)
             }
-            (BasicToken::Fraction(mut la), BasicToken::CIntRoot(ra)) => {
-                la.num = add!(la.num, mul!(la.int, la.den));
-                la.den = mul!(la.den, ra.mul);
-                la.int = 0;
-                BasicToken::new_c_root(la.normalise()?, ra.base)
-            }
             (BasicToken::CFracRoot(la), BasicToken::Fraction(ra)) => {
                 BasicToken::new_c_root((la.mul / ra)?, la.base)
             }
-            (BasicToken::Fraction(la), BasicToken::CFracRoot(ra)) => {
-                BasicToken::new_c_root((la / ra.mul)?, ra.base)
-            }
-            (BasicToken::CIntRoot(la), BasicToken::CIntRoot(ra)) => {
-                let mut frac = Fraction::new(0, la.mul, mul!(ra.mul, ra.base));
-                let res = factorise(mul!(la.base, ra.base), false);
-                frac.num = mul!(res.outside, frac.num);
-                BasicToken::new_c_root(frac.normalise()?, res.inside)
-            }
-            (BasicToken::CFracRoot(mut la), BasicToken::CIntRoot(ra)) => {
-                la.mul.num = add!(la.mul.num, mul!(la.mul.int, la.mul.den));
-                la.mul.den = mul!(mul!(la.mul.den, ra.mul), ra.base);
-                la.mul.int = 0;
-                let res = factorise(mul!(la.base, ra.base), false);
-                la.mul.num = mul!(la.mul.num, res.outside);
-                BasicToken::new_c_root(la.mul.normalise()?, res.inside)
-            }
-            (BasicToken::CIntRoot(la), BasicToken::CFracRoot(ra)) => {
-                let mut frac = Fraction::new(
-                    0,
-                    mul!(la.mul, ra.mul.den),
-                    mul!(add!(ra.mul.num, mul!(ra.mul.den, ra.mul.int)), ra.base),
-                );
-                let res = factorise(mul!(la.base, ra.base), false);
-                frac.num = mul!(frac.num, res.outside);
-                BasicToken::new_c_root(frac.normalise()?, res.inside)
-            }
-            (BasicToken::CFracRoot(mut la), BasicToken::CFracRoot(mut ra)) => {
-                ra.mul.num = mul!(ra.mul.num, ra.base);
-                ra.mul.int = mul!(ra.mul.int, ra.base);
-                match ra.mul.normalise()? {
-                    BasicToken::Integer(int) => {
-                        la.mul.num = add!(la.mul.num, mul!(la.mul.int, la.mul.den));
-                        la.mul.den = mul!(la.mul.den, int); // Already have multiplied by base so no need to do it again
-                        la.mul.int = 0;
-                        let res = factorise(mul!(la.base, ra.base), false);
-                        la.mul.num = mul!(la.mul.num, res.outside);
-                        BasicToken::new_c_root(la.mul.normalise()?, res.inside)
-                    }
-                    BasicToken::Fraction(frac) => {
-                        let res = factorise(mul!(la.base, ra.base), false);
-                        la.mul.int = mul!(la.mul.int, res.outside);
-                        la.mul.num = mul!(la.mul.int, res.outside);
-                        BasicToken::new_c_root((la.mul / frac)?, res.inside)
-                    }
-                    _ => unreachable!(),
-                }
-            }
             _ => Err(MathError::Overflow),
         }
     }
 }
 
 pub fn div(l_number: Token, r_number: Token) -> Result<Token, MathError> {
+    steps::operation('÷', l_number, r_number, div_tokens)
+}
+
+fn div_tokens(l_number: Token, r_number: Token) -> Result<Token, MathError> {
     match (l_number, r_number) {
         (Token::Basic(BasicToken::Integer(0)), _) => Ok(Token::Basic(BasicToken::Integer(0))),
         (_, Token::Basic(BasicToken::Integer(0))) => Err(MathError::DivisionByZero),
-        (Token::Pi(l_number), Token::Pi(r_number))
-        | (Token::Basic(l_number), Token::Basic(r_number)) => match try_div(l_number, r_number) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                l_number.double() / r_number.double()
-            )))),
+        (Token::Pi(l_number), Token::Pi(r_number)) => match try_div(l_number, r_number) {
+            Err(MathError::Overflow) => inexact(
+                Operation::Divide,
+                vec![Token::Pi(l_number), Token::Pi(r_number)],
+                LossReason::Overflow,
+                l_number.double() / r_number.double(),
+            ),
+            value => Ok(Token::Basic(value?)),
+        },
+        (Token::Basic(l_number), Token::Basic(r_number)) => match try_div(l_number, r_number) {
+            Err(MathError::Overflow) => inexact(
+                Operation::Divide,
+                vec![Token::Basic(l_number), Token::Basic(r_number)],
+                LossReason::Overflow,
+                l_number.double() / r_number.double(),
+            ),
             value => Ok(Token::Basic(value?)),
         },
         (Token::Pi(l_number), Token::Basic(r_number)) => match try_div(l_number, r_number) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!(
-                (l_number.double() * std::f64::consts::PI) / r_number.double()
-            )))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Divide,
+                vec![Token::Pi(l_number), Token::Basic(r_number)],
+                LossReason::Overflow,
+                (l_number.double() * std::f64::consts::PI) / r_number.double(),
+            ),
             value => Ok(Token::Pi(value?)),
         },
-        (Token::Basic(l_number), Token::Pi(r_number)) => Ok(Token::Basic(BasicToken::Double(
-            double_check!(l_number.double() / (r_number.double() * std::f64::consts::PI)),
-        ))),
-        (Token::Basic(l_number), Token::Combined(r_number)) => Ok(Token::Basic(
-            BasicToken::Double(double_check!(l_number.double() / r_number.double())),
-        )),
-        (Token::Pi(l_number), Token::Combined(r_number)) => Ok(Token::Basic(BasicToken::Double(
-            double_check!((l_number.double() * std::f64::consts::PI) / r_number.double()),
-        ))),
-        (Token::Combined(l_number), Token::Combined(r_number)) => Ok(Token::Basic(
-            BasicToken::Double(double_check!(l_number.double() / r_number.double())),
-        )),
+        // a + b√c is rationalised with its conjugate a - b√c.
+        (l_number, Token::Combined(r_number)) if conjugate(&r_number).is_some() => {
+            let (conjugate, denominator) = conjugate(&r_number).unwrap();
+            let numerator = match l_number {
+                Token::Combined(l_number) => {
+                    let terms = l_number.basic.vec.iter().map(|basic| Token::Basic(*basic));
+                    let terms = terms.chain(l_number.pi.vec.iter().map(|pi| Token::Pi(*pi)));
+                    let mut numerator = Token::Basic(BasicToken::Integer(0));
+                    for term in terms {
+                        numerator = add_tokens(numerator, mul_tokens(term, conjugate.clone())?)?;
+                    }
+                    numerator
+                }
+                l_number => mul_tokens(l_number, conjugate)?,
+            };
+            div_tokens(numerator, Token::Basic(denominator))
+        }
+        (l_number @ Token::Basic(_), r_number @ Token::Pi(_))
+        | (l_number @ Token::Basic(_), r_number @ Token::Combined(_))
+        | (l_number @ Token::Pi(_), r_number @ Token::Combined(_))
+        | (l_number @ Token::Combined(_), r_number @ Token::Combined(_)) => {
+            let value = l_number.double() / r_number.double();
+            inexact(
+                Operation::Divide,
+                vec![l_number, r_number],
+                LossReason::NotRepresentable,
+                value,
+            )
+        }
         (Token::Combined(l_number), Token::Basic(r_number)) => {
             let mut basic = Set::new(vec![]);
             let mut pi = Set::new(vec![]);
             for basic_tok in &l_number.basic.vec {
                 basic.vec.push(match try_div(*basic_tok, r_number) {
                     Err(MathError::Overflow) => {
-                        return Ok(Token::Basic(BasicToken::Double(double_check!(
-                            l_number.double() / r_number.double()
-                        ))))
+                        return inexact(
+                            Operation::Divide,
+                            vec![Token::Combined(l_number.clone()), Token::Basic(r_number)],
+                            LossReason::Overflow,
+                            l_number.double() / r_number.double(),
+                        )
                     }
                     val => val?,
                 })
@@ -502,9 +501,12 @@ pub fn div(l_number: Token, r_number: Token) -> Result<Token, MathError> {
             for pi_tok in &l_number.pi.vec {
                 pi.vec.push(match try_div(*pi_tok, r_number) {
                     Err(MathError::Overflow) => {
-                        return Ok(Token::Basic(BasicToken::Double(double_check!(
-                            l_number.double() / r_number.double()
-                        ))))
+                        return inexact(
+                            Operation::Divide,
+                            vec![Token::Combined(l_number.clone()), Token::Basic(r_number)],
+                            LossReason::Overflow,
+                            l_number.double() / r_number.double(),
+                        )
                     }
                     val => val?,
                 })
@@ -516,9 +518,12 @@ pub fn div(l_number: Token, r_number: Token) -> Result<Token, MathError> {
             for pi_tok in &l_number.pi.vec {
                 basic.vec.push(match try_div(*pi_tok, r_number) {
                     Err(MathError::Overflow) => {
-                        return Ok(Token::Basic(BasicToken::Double(double_check!(
-                            l_number.double() / r_number.double()
-                        ))))
+                        return inexact(
+                            Operation::Divide,
+                            vec![Token::Combined(l_number.clone()), Token::Pi(r_number)],
+                            LossReason::Overflow,
+                            l_number.double() / r_number.double(),
+                        )
                     }
                     val => val?,
                 })
@@ -529,9 +534,15 @@ pub fn div(l_number: Token, r_number: Token) -> Result<Token, MathError> {
             }
             .normalise())
         }
-        (Token::Combined(l_number), Token::Pi(r_number)) => Ok(Token::Basic(BasicToken::Double(
-            double_check!(l_number.double() / (r_number.double() * std::f64::consts::PI)),
-        ))),
+        (Token::Combined(l_number), Token::Pi(r_number)) => {
+            let value = l_number.double() / (r_number.double() * std::f64::consts::PI);
+            inexact(
+                Operation::Divide,
+                vec![Token::Combined(l_number), Token::Pi(r_number)],
+                LossReason::NotRepresentable,
+                value,
+            )
+        }
     }
 }
 
@@ -654,65 +665,43 @@ fn try_exp(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
                 _ => unreachable!(),
             }
         }
-        (BasicToken::SIntRoot(la), BasicToken::Integer(mut ra)) => {
-            let negative = ra < 0;
-            ra = abs!(ra);
+        (
+            root @ (BasicToken::SIntRoot(_)
+            | BasicToken::SFracRoot(_)
+            | BasicToken::CIntRoot(_)
+            | BasicToken::CFracRoot(_)),
+            BasicToken::Integer(ra),
+        ) if ra < 0 => try_div(
+            BasicToken::Integer(1),
+            try_exp(root, BasicToken::Integer(-ra))?,
+        ),
+        (BasicToken::SIntRoot(la), BasicToken::Integer(ra)) => {
             let mul = mul!(pow!(la.mul, ra), pow!(la.base, ra / 2));
-            match (negative, ra % 2) {
-                (true, 0) => Ok(BasicToken::fraction(0, 1, mul)),
-                (false, 0) => Ok(BasicToken::Integer(mul)),
-                (true, _) => Ok(BasicToken::s_frac_root(0, 1, mul, la.base)),
-                (false, _) => Ok(BasicToken::s_int_root(mul, la.base)),
-            }
-        }
-        (BasicToken::CIntRoot(la), BasicToken::Integer(mut ra)) => {
-            let negative = ra < 0;
-            ra = abs!(ra);
-            let mul = mul!(pow!(la.mul, ra), pow!(la.base, ra / 3));
-            match (negative, ra % 3) {
-                (true, 0) => Ok(BasicToken::fraction(0, 1, mul)),
-                (false, 0) => Ok(BasicToken::Integer(mul)),
-                (true, 1) => Ok(BasicToken::c_frac_root(0, 1, mul, la.base)),
-                (false, 1) => Ok(BasicToken::c_int_root(mul, la.base)),
-                (true, _) => Ok(BasicToken::c_frac_root(0, 1, mul, pow!(la.base, 2))),
-                (false, _) => Ok(BasicToken::c_int_root(mul, pow!(la.base, 2))),
-            }
-        }
-        (BasicToken::SFracRoot(la), BasicToken::Integer(mut ra)) => {
-            let negative = ra < 0;
-            ra = abs!(ra);
-            let num = la.mul.int * la.mul.den + la.mul.num;
-            let mul_num = mul!(pow!(num, ra), pow!(la.base, ra / 2));
-            let mul_den = pow!(la.mul.den, ra);
-
-            let res = match negative {
-                true => Fraction::new(0, mul_den, mul_num),
-                false => Fraction::new(0, mul_den, mul_num),
-            }
-            .normalise()?;
-
-            if ra % 2 == 0 {
-                Ok(res)
-            } else {
-                BasicToken::new_c_root(res, la.base)
-            }
-        }
-        (BasicToken::CFracRoot(la), BasicToken::Integer(mut ra)) => {
-            let negative = ra < 0;
-            ra = abs!(ra);
-            let num = la.mul.int * la.mul.den + la.mul.num;
-            let mul_num = mul!(pow!(num, ra), pow!(la.base, ra / 3));
-            let mul_den = pow!(la.mul.den, ra);
-
-            let res = match negative {
-                true => Fraction::new(0, mul_den, mul_num),
-                false => Fraction::new(0, mul_den, mul_num),
-            }
-            .normalise()?;
-
+            match ra % 2 {
+                0 => Ok(BasicToken::Integer(mul)),
+                _ => Ok(BasicToken::s_int_root(mul, la.base)),
+            }
+        }
+        (BasicToken::SFracRoot(la), BasicToken::Integer(ra)) => {
+            let outside = try_exp(BasicToken::Fraction(la.mul), BasicToken::Integer(ra))?;
+            let outside = try_mul(outside, BasicToken::Integer(pow!(la.base, ra / 2)))?;
+            match ra % 2 {
+                0 => Ok(outside),
+                _ => try_mul(outside, BasicToken::s_int_root(1, la.base)),
+            }
+        }
+        (BasicToken::CIntRoot(CRoot { mul, base }), BasicToken::Integer(ra)) => try_exp(
+            BasicToken::c_frac_root(0, mul, 1, base),
+            BasicToken::Integer(ra),
+        ),
+        (BasicToken::CFracRoot(la), BasicToken::Integer(ra)) => {
+            let outside = try_exp(BasicToken::Fraction(la.mul), BasicToken::Integer(ra))?;
+            let outside = try_mul(outside, BasicToken::Integer(pow!(la.base, ra / 3)))?;
+            let root = BasicToken::c_int_root(1, la.base);
             match ra % 3 {
-                0 => Ok(res),
-                _ => BasicToken::new_c_root(res, pow!(la.base, ra % 3)),
+                0 => Ok(outside),
+                1 => try_mul(outside, root),
+                _ => try_mul(outside, try_mul(root, root)?),
             }
         }
         _ => Err(MathError::Overflow),
@@ -720,6 +709,16 @@ fn try_exp(lhs: BasicToken, rhs: BasicToken) -> Result<BasicToken, MathError> {
 }
 
 pub fn exp(l_number: Token, r_number: Token) -> Result<Token, MathError> {
+    match r_number {
+        Token::Basic(BasicToken::Integer(_)) => {
+            steps::operation('^', l_number, r_number, exp_tokens)
+        }
+        // Roots are explained by the steps that simplify them.
+        _ => exp_tokens(l_number, r_number),
+    }
+}
+
+fn exp_tokens(l_number: Token, r_number: Token) -> Result<Token, MathError> {
     match (l_number, r_number) {
         // Check if 0^0.
         (Token::Basic(BasicToken::Integer(0)), Token::Basic(BasicToken::Integer(0))) => {
@@ -743,13 +742,211 @@ pub fn exp(l_number: Token, r_number: Token) -> Result<Token, MathError> {
         (x, Token::Basic(BasicToken::Integer(1))) => Ok(x),
 
         (Token::Basic(lhs), Token::Basic(rhs)) => match try_exp(lhs, rhs) {
-            Err(MathError::Overflow) => Ok(Token::Basic(BasicToken::Double(double_check!((lhs
-                .double())
-            .powf(rhs.double()))))),
+            Err(MathError::Overflow) => inexact(
+                Operation::Power,
+                vec![Token::Basic(lhs), Token::Basic(rhs)],
+                exp_loss_reason(lhs, rhs),
+                lhs.double().powf(rhs.double()),
+            ),
             value => Ok(Token::Basic(value?)),
         },
-        (lhs, rhs) => Ok(Token::Basic(BasicToken::Double(double_check!(lhs
-            .double()
-            .powf(rhs.double()))))),
+        (lhs, rhs) => {
+            let value = lhs.double().powf(rhs.double());
+            inexact(
+                Operation::Power,
+                vec![lhs, rhs],
+                LossReason::NotRepresentable,
+                value,
+            )
+        }
+    }
+}
+
+/// Works out why [`try_exp`] could not give an exact answer.
+fn exp_loss_reason(lhs: BasicToken, rhs: BasicToken) -> LossReason {
+    match (lhs, rhs) {
+        (BasicToken::Integer(_) | BasicToken::Fraction(_), BasicToken::Fraction(ra))
+            if ra.den != 2 && ra.den != 3 =>
+        {
+            LossReason::UnsupportedRoot(ra.den)
+        }
+        (_, BasicToken::Integer(_))
+        | (BasicToken::Integer(_) | BasicToken::Fraction(_), BasicToken::Fraction(_)) => {
+            LossReason::Overflow
+        }
+        _ => LossReason::NotRepresentable,
     }
 }
+
+#[inline]
+fn sign(value: f64) -> Ordering {
+    value.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+}
+
+/// The sign of a single term. A fraction whose whole part and numerator have different signs can
+/// be too close to zero for its double, e.g. `-1 + (n - 1)/n`.
+fn term_sign(tok: &BasicToken) -> Ordering {
+    let fraction = match tok {
+        BasicToken::Integer(i) => return i.cmp(&0),
+        BasicToken::SIntRoot(r) => return r.mul.cmp(&0),
+        BasicToken::CIntRoot(r) => return r.mul.cmp(&0),
+        BasicToken::Double(d) => return sign(*d),
+        BasicToken::Fraction(f) => f,
+        BasicToken::SFracRoot(r) => &r.mul,
+        BasicToken::CFracRoot(r) => &r.mul,
+    };
+    match fraction
+        .int
+        .checked_mul(fraction.den)
+        .and_then(|int| int.checked_add(fraction.num))
+    {
+        Some(value) => value.cmp(&0),
+        // The numerator is smaller than the denominator, so a large whole part decides.
+        None => fraction.int.cmp(&0),
+    }
+}
+
+/// The square of a rational or a square root, `b²c` is computed from `b` and `c` so that `c` is
+/// not squared as well.
+fn square(value: BasicToken) -> Result<BasicToken, MathError> {
+    let (mul, base) = match value {
+        BasicToken::SIntRoot(r) => (BasicToken::Integer(r.mul), r.base),
+        BasicToken::SFracRoot(r) => (BasicToken::Fraction(r.mul), r.base),
+        _ => return try_mul(value, value),
+    };
+    try_mul(try_mul(mul, mul)?, BasicToken::Integer(base))
+}
+
+/// The most terms [`root_sum_sign`] works with before giving up.
+const MAX_ROOT_TERMS: usize = 32;
+
+/// The sum of the `terms` squared, with `b²c` computed as in [`square`].
+fn square_sum(terms: &[BasicToken]) -> Result<Set<BasicToken>, MathError> {
+    let mut sum = Set::new(vec![]);
+    for (i, lhs) in terms.iter().enumerate() {
+        sum.add(square(*lhs)?)?;
+        for rhs in &terms[i + 1..] {
+            sum.add(try_mul(BasicToken::Integer(2), try_mul(*lhs, *rhs)?)?)?;
+        }
+    }
+    Ok(sum)
+}
+
+/// The base of a term that is a square root, 1 for a rational.
+fn root_base(tok: &BasicToken) -> Option<i128> {
+    match tok {
+        BasicToken::Integer(_) | BasicToken::Fraction(_) => Some(1),
+        BasicToken::SIntRoot(r) => Some(r.base),
+        BasicToken::SFracRoot(r) => Some(r.base),
+        _ => None,
+    }
+}
+
+/// A factor `d` of one of the bases that every other base is either a multiple of or has no
+/// factor in common with, so each term is a multiple of `√d` or has no `√d` in it.
+fn common_factor(bases: &[i128]) -> Option<i128> {
+    let mut factor = *bases.iter().find(|base| **base > 1)?;
+    loop {
+        match bases
+            .iter()
+            .map(|base| (*base as u128).gcd(factor as u128) as i128)
+            .find(|gcd| *gcd != 1 && *gcd != factor)
+        {
+            Some(smaller) => factor = smaller,
+            None => return Some(factor),
+        }
+    }
+}
+
+/// `tok/√d` for a root whose base is a multiple of `d`.
+fn without_root(tok: BasicToken, d: i128) -> Result<BasicToken, MathError> {
+    match tok {
+        BasicToken::SIntRoot(r) => Ok(SRoot::new(r.mul, r.base / d).normalise()),
+        BasicToken::SFracRoot(r) => SRoot::new(r.mul, r.base / d).normalise(),
+        _ => unreachable!(),
+    }
+}
+
+/// The sign of a sum of rationals and square roots.
+///
+/// The terms are split into the multiples `b` of some `√d` and the rest `a`. When `a` and `b` have
+/// different signs the larger of `a²` and `b²` decides, and neither of them has `√d` in it. Each
+/// step leaves fewer factors under the roots, so this ends with terms that all have one sign.
+fn root_sum_sign(terms: &[BasicToken]) -> Result<Ordering, LossReason> {
+    let overflow = |_| LossReason::Overflow;
+    let bases = terms
+        .iter()
+        .map(root_base)
+        .collect::<Option<Vec<i128>>>()
+        .filter(|_| terms.len() <= MAX_ROOT_TERMS)
+        .ok_or(LossReason::UnknownSign)?;
+    let mut signs = terms.iter().map(term_sign);
+    let first = signs.next().unwrap_or(Ordering::Equal);
+    if signs.all(|sign| sign == first) {
+        return Ok(first);
+    }
+    let factor = common_factor(&bases).ok_or(LossReason::UnknownSign)?;
+    let (mut multiples, mut others) = (vec![], vec![]);
+    for (tok, base) in terms.iter().zip(bases) {
+        match base % factor {
+            0 => multiples.push(*tok),
+            _ => others.push(*tok),
+        }
+    }
+    let reduced = multiples
+        .iter()
+        .map(|tok| without_root(*tok, factor))
+        .collect::<Result<Vec<BasicToken>, MathError>>()
+        .map_err(overflow)?;
+    let (a, b) = (root_sum_sign(&others)?, root_sum_sign(&reduced)?);
+    if a == b || b == Ordering::Equal {
+        return Ok(a);
+    }
+    if a == Ordering::Equal {
+        return Ok(b);
+    }
+    let mut difference = square_sum(&others).map_err(overflow)?;
+    for tok in square_sum(&multiples).map_err(overflow)?.vec {
+        difference
+            .add(tok.negate().map_err(overflow)?)
+            .map_err(overflow)?;
+    }
+    Ok(match root_sum_sign(&difference.vec)? {
+        Ordering::Greater => a,
+        Ordering::Less => b,
+        Ordering::Equal => Ordering::Equal,
+    })
+}
+
+/// Compares two numbers without rounding their difference.
+///
+/// A single term has its own sign, and so does a sum whose terms all have the same sign. Sums of
+/// rationals and square roots are squared until that is the case, see [`root_sum_sign`]. Other sums, such as those with multiples of π, are compared by their double,
+/// which is recorded as a precision loss.
+pub fn compare(lhs: &Token, rhs: &Token) -> Result<Ordering, MathError> {
+    let difference = sub(lhs.clone(), rhs.clone())?;
+    let combined = match &difference {
+        Token::Combined(combined) => combined,
+        Token::Basic(tok) | Token::Pi(tok) => return Ok(term_sign(tok)),
+    };
+    let mut signs = combined
+        .basic
+        .vec
+        .iter()
+        .chain(combined.pi.vec.iter())
+        .map(term_sign);
+    let first = signs.next().unwrap_or(Ordering::Equal);
+    if signs.all(|sign| sign == first) {
+        return Ok(first);
+    }
+    let reason = match combined.pi.vec.is_empty() {
+        // Strict so that an overflow fails instead of rounding to a double.
+        true => match track(true, || root_sum_sign(&combined.basic.vec)).0 {
+            Ok(ordering) => return Ok(ordering),
+            Err(reason) => reason,
+        },
+        false => LossReason::UnknownSign,
+    };
+    record(Operation::Compare, vec![lhs.clone(), rhs.clone()], reason)?;
+    Ok(sign(difference.double()))
+}
@@ -0,0 +1,319 @@
+//! Polynomials in any number of symbols with exact [`Token`] coefficients.
+//!
+//! Polynomials are always kept expanded with like terms combined, so two equal polynomials
+//! have the same terms no matter how they were written.
+
+use crate::number::{add, div, mul};
+use crate::types::{BasicToken, MathError, Token};
+use core::fmt;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+/// The symbols of a term with their powers, sorted by symbol. The constant term has none.
+pub type Monomial = Vec<(String, u32)>;
+
+fn monomial_mul(lhs: &Monomial, rhs: &Monomial) -> Result<Monomial, MathError> {
+    let mut product = lhs.clone();
+    for (symbol, power) in rhs {
+        match product.binary_search_by(|(other, _)| other.cmp(symbol)) {
+            Ok(pos) => {
+                product[pos].1 = product[pos]
+                    .1
+                    .checked_add(*power)
+                    .ok_or(MathError::Overflow)?
+            }
+            Err(pos) => product.insert(pos, (symbol.clone(), *power)),
+        }
+    }
+    Ok(product)
+}
+
+fn degree(monomial: &Monomial) -> u32 {
+    monomial.iter().map(|(_, power)| power).sum()
+}
+
+#[derive(Debug, Clone)]
+pub struct Polynomial {
+    terms: BTreeMap<Monomial, Token>,
+    /// The symbol the terms are grouped by when displayed, see [`Polynomial::collect`].
+    collected: Option<String>,
+}
+
+impl PartialEq for Polynomial {
+    fn eq(&self, other: &Polynomial) -> bool {
+        self.terms == other.terms
+    }
+}
+
+impl Polynomial {
+    fn zero() -> Polynomial {
+        Polynomial {
+            terms: BTreeMap::new(),
+            collected: None,
+        }
+    }
+
+    pub fn constant(value: Token) -> Polynomial {
+        let mut polynomial = Polynomial::zero();
+        if !value.is_zero() {
+            polynomial.terms.insert(vec![], value);
+        }
+        polynomial
+    }
+
+    pub fn symbol(name: &str) -> Polynomial {
+        let mut polynomial = Polynomial::zero();
+        polynomial.terms.insert(
+            vec![(name.to_string(), 1)],
+            Token::Basic(BasicToken::Integer(1)),
+        );
+        polynomial
+    }
+
+    /// Every term with a non zero coefficient.
+    pub fn terms(&self) -> impl Iterator<Item = (&Monomial, &Token)> {
+        self.terms.iter()
+    }
+
+    /// The value of the polynomial if it does not contain any symbols.
+    pub fn as_constant(&self) -> Option<Token> {
+        match self.terms.len() {
+            0 => Some(Token::Basic(BasicToken::Integer(0))),
+            1 => self.terms.get(&vec![]).cloned(),
+            _ => None,
+        }
+    }
+
+    /// All symbols used, sorted.
+    pub fn symbols(&self) -> Vec<&str> {
+        let mut symbols: Vec<&str> = self
+            .terms
+            .keys()
+            .flatten()
+            .map(|(symbol, _)| symbol.as_str())
+            .collect();
+        symbols.sort_unstable();
+        symbols.dedup();
+        symbols
+    }
+
+    /// The highest power of `symbol`.
+    pub fn degree(&self, symbol: &str) -> u32 {
+        self.terms
+            .keys()
+            .flatten()
+            .filter(|(other, _)| other == symbol)
+            .map(|(_, power)| *power)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The coefficients of each power of `symbol`, starting with the terms without it.
+    pub fn coefficients(&self, symbol: &str) -> Result<Vec<Polynomial>, MathError> {
+        let mut coefficients = vec![Polynomial::zero(); self.degree(symbol) as usize + 1];
+        for (monomial, coefficient) in &self.terms {
+            let mut rest = monomial.clone();
+            let power = match rest.iter().position(|(other, _)| other == symbol) {
+                Some(pos) => rest.remove(pos).1,
+                None => 0,
+            };
+            coefficients[power as usize].insert(rest, coefficient.clone())?;
+        }
+        Ok(coefficients)
+    }
+
+    /// Undoes [`Polynomial::collect`].
+    pub fn expand(mut self) -> Polynomial {
+        self.collected = None;
+        self
+    }
+
+    /// Groups the terms by the powers of `symbol` when displayed, e.g. `(y + 1)x^2 + y`.
+    pub fn collect(mut self, symbol: &str) -> Polynomial {
+        self.collected = Some(symbol.to_string());
+        self
+    }
+
+    fn insert(&mut self, monomial: Monomial, coefficient: Token) -> Result<(), MathError> {
+        let sum = match self.terms.remove(&monomial) {
+            Some(existing) => add(existing, coefficient)?,
+            None => coefficient,
+        };
+        if !sum.is_zero() {
+            self.terms.insert(monomial, sum);
+        }
+        Ok(())
+    }
+
+    fn map_coefficients(
+        self,
+        f: impl Fn(Token) -> Result<Token, MathError>,
+    ) -> Result<Polynomial, MathError> {
+        let mut result = Polynomial::zero();
+        for (monomial, coefficient) in self.terms {
+            result.insert(monomial, f(coefficient)?)?;
+        }
+        Ok(result)
+    }
+
+    pub fn plus(mut self, other: Polynomial) -> Result<Polynomial, MathError> {
+        self.collected = None;
+        for (monomial, coefficient) in other.terms {
+            self.insert(monomial, coefficient)?;
+        }
+        Ok(self)
+    }
+
+    pub fn negate(self) -> Result<Polynomial, MathError> {
+        self.map_coefficients(|coefficient| mul(coefficient, Token::Basic(BasicToken::Integer(-1))))
+    }
+
+    pub fn minus(self, other: Polynomial) -> Result<Polynomial, MathError> {
+        self.plus(other.negate()?)
+    }
+
+    pub fn times(&self, other: &Polynomial) -> Result<Polynomial, MathError> {
+        let mut product = Polynomial::zero();
+        for (l_monomial, l_coefficient) in &self.terms {
+            for (r_monomial, r_coefficient) in &other.terms {
+                product.insert(
+                    monomial_mul(l_monomial, r_monomial)?,
+                    mul(l_coefficient.clone(), r_coefficient.clone())?,
+                )?;
+            }
+        }
+        Ok(product)
+    }
+
+    /// Divides every coefficient by `divisor`.
+    pub fn divide(self, divisor: &Token) -> Result<Polynomial, MathError> {
+        self.map_coefficients(|coefficient| div(coefficient, divisor.clone()))
+    }
+
+    pub fn pow(&self, mut exponent: u32) -> Result<Polynomial, MathError> {
+        let mut result = Polynomial::constant(Token::Basic(BasicToken::Integer(1)));
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result.times(&base)?;
+            }
+            exponent /= 2;
+            if exponent > 0 {
+                base = base.times(&base)?;
+            }
+        }
+        Ok(result)
+    }
+
+    /// The terms in the order they are displayed, highest degree first.
+    pub(crate) fn sorted_terms(&self) -> Vec<(&Monomial, &Token)> {
+        let mut terms: Vec<_> = self.terms.iter().collect();
+        terms.sort_by_key(|(monomial, _)| {
+            (
+                Reverse(degree(monomial)),
+                monomial
+                    .iter()
+                    .map(|(symbol, power)| (symbol, Reverse(*power)))
+                    .collect::<Vec<_>>(),
+            )
+        });
+        terms
+    }
+}
+
+fn fmt_monomial(monomial: &Monomial) -> String {
+    let separator = match monomial.iter().all(|(symbol, _)| symbol.len() == 1) {
+        true => "",
+        false => "*",
+    };
+    monomial
+        .iter()
+        .map(|(symbol, power)| match power {
+            1 => symbol.clone(),
+            _ => format!("{}^{}", symbol, power),
+        })
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn fmt_term(monomial: &Monomial, coefficient: &Token) -> String {
+    match (monomial.is_empty(), coefficient) {
+        (true, _) => coefficient.to_string(),
+        (false, Token::Basic(BasicToken::Integer(1))) => fmt_monomial(monomial),
+        (false, Token::Basic(BasicToken::Integer(-1))) => format!("-{}", fmt_monomial(monomial)),
+        (false, Token::Combined(_)) => format!("({}){}", coefficient, fmt_monomial(monomial)),
+        // The denominator goes after the symbols, as in 3x^2/4 and -x/2.
+        (false, _) => {
+            let coefficient = coefficient.to_string();
+            let (num, den) = match coefficient.rsplit_once('/') {
+                Some((num, den)) => (num, format!("/{}", den)),
+                None => (coefficient.as_str(), String::new()),
+            };
+            let num = match num {
+                "1" => "",
+                "-1" => "-",
+                num => num,
+            };
+            format!("{}{}{}", num, fmt_monomial(monomial), den)
+        }
+    }
+}
+
+/// Joins terms with + or -, depending on the sign each term starts with.
+fn join_terms(terms: Vec<String>) -> String {
+    let mut result = String::new();
+    for (pos, term) in terms.into_iter().enumerate() {
+        match (pos, term.strip_prefix('-')) {
+            (0, _) => result.push_str(&term),
+            (_, Some(negated)) => {
+                result.push_str(" - ");
+                result.push_str(negated);
+            }
+            (_, None) => {
+                result.push_str(" + ");
+                result.push_str(&term);
+            }
+        }
+    }
+    result
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        let symbol = match &self.collected {
+            Some(symbol) => symbol,
+            None => {
+                let terms = self.sorted_terms();
+                let terms = terms
+                    .into_iter()
+                    .map(|(monomial, coefficient)| fmt_term(monomial, coefficient))
+                    .collect();
+                return write!(f, "{}", join_terms(terms));
+            }
+        };
+        let coefficients = self.coefficients(symbol).map_err(|_| fmt::Error)?;
+        let mut terms = vec![];
+        for (power, coefficient) in coefficients.into_iter().enumerate().rev() {
+            let power = power as u32;
+            let symbol_power = match power {
+                0 => vec![],
+                _ => vec![(symbol.clone(), power)],
+            };
+            match coefficient.terms.len() {
+                0 => {}
+                1 => {
+                    let (monomial, value) = coefficient.terms.into_iter().next().unwrap();
+                    let monomial =
+                        monomial_mul(&monomial, &symbol_power).map_err(|_| fmt::Error)?;
+                    terms.push(fmt_term(&monomial, &value));
+                }
+                _ if power == 0 => terms.push(coefficient.to_string()),
+                _ => terms.push(format!("({}){}", coefficient, fmt_monomial(&symbol_power))),
+            }
+        }
+        write!(f, "{}", join_terms(terms))
+    }
+}
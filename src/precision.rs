@@ -0,0 +1,192 @@
+use crate::types::{BasicToken, MathError, Token};
+use core::fmt;
+use std::cell::{Cell, RefCell};
+
+/// The operation that had to fall back to a double.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    /// Deciding which of two numbers is larger.
+    Compare,
+    /// Drawing a number with `rand()`.
+    Random,
+    Literal(String),
+    /// A root found by [`crate::nsolve`].
+    RootFinding(Convergence),
+    /// An integral computed by [`crate::integrate`].
+    Integration(Convergence),
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Add => write!(f, "Addition"),
+            Operation::Subtract => write!(f, "Subtraction"),
+            Operation::Multiply => write!(f, "Multiplication"),
+            Operation::Divide => write!(f, "Division"),
+            Operation::Power => write!(f, "Exponentiation"),
+            Operation::Sin => write!(f, "sin"),
+            Operation::Cos => write!(f, "cos"),
+            Operation::Tan => write!(f, "tan"),
+            Operation::Asin => write!(f, "asin"),
+            Operation::Acos => write!(f, "acos"),
+            Operation::Atan => write!(f, "atan"),
+            Operation::Compare => write!(f, "Comparison"),
+            Operation::Random => write!(f, "A random number"),
+            Operation::Literal(literal) => write!(f, "The number {}", literal),
+            Operation::RootFinding(convergence) => write!(f, "Root finding ({})", convergence),
+            Operation::Integration(convergence) => write!(f, "Integration ({})", convergence),
+        }
+    }
+}
+
+/// How a numerical method converged.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Convergence {
+    pub iterations: u32,
+    /// An estimate of the absolute error of the result.
+    pub error: f64,
+}
+
+impl fmt::Display for Convergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} iterations, error about {:e}",
+            self.iterations, self.error
+        )
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum LossReason {
+    /// An intermediate value did not fit in an i128.
+    Overflow,
+    /// Only square and cube roots can be stored exactly, this holds the order of the root.
+    UnsupportedRoot(i128),
+    /// The angle is not one of the angles with a known exact value.
+    NonTableAngle,
+    /// The value is not the exact sin, cos or tan of a known angle.
+    NonTableValue,
+    /// The result cannot be written with the exact types, e.g. π*π or 1/π.
+    NotRepresentable,
+    /// The sign of a sum of different radicals or multiples of π cannot be found exactly.
+    UnknownSign,
+    /// The equation has no exact solution so it was solved numerically.
+    NoClosedForm,
+    /// The integrand has no known antiderivative so it was integrated numerically.
+    NoAntiderivative,
+    /// Numbers from `rand()` are drawn as doubles.
+    Random,
+}
+
+impl fmt::Display for LossReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LossReason::Overflow => write!(f, "i128 overflow"),
+            LossReason::UnsupportedRoot(order) => {
+                write!(f, "roots of order {} are not supported", order)
+            }
+            LossReason::NonTableAngle => write!(f, "angle has no known exact value"),
+            LossReason::NonTableValue => write!(f, "value is not a known exact trig value"),
+            LossReason::NotRepresentable => write!(f, "result has no exact representation"),
+            LossReason::UnknownSign => write!(f, "the sign of the difference is not known exactly"),
+            LossReason::NoClosedForm => write!(f, "no exact solution was found"),
+            LossReason::NoAntiderivative => write!(f, "no antiderivative was found"),
+            LossReason::Random => write!(f, "random numbers are drawn as doubles"),
+        }
+    }
+}
+
+/// Records that an operation returned a [`BasicToken::Double`] even though its operands were exact.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PrecisionLoss {
+    pub operation: Operation,
+    pub operands: Vec<Token>,
+    pub reason: LossReason,
+}
+
+impl fmt::Display for PrecisionLoss {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        for (pos, operand) in self.operands.iter().enumerate() {
+            match pos {
+                0 => write!(f, " of {}", operand)?,
+                _ => write!(f, " and {}", operand)?,
+            }
+        }
+        write!(f, " was rounded: {}", self.reason)
+    }
+}
+
+thread_local! {
+    static LOSSES: RefCell<Option<Vec<PrecisionLoss>>> = const { RefCell::new(None) };
+    static STRICT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` and returns every precision loss that happened during it.
+///
+/// When `strict` is set any operation that cannot be computed exactly fails with
+/// [`MathError::Inexact`] instead of returning a double.
+pub fn track<T>(strict: bool, f: impl FnOnce() -> T) -> (T, Vec<PrecisionLoss>) {
+    let outer = LOSSES.with(|losses| losses.replace(Some(vec![])));
+    let outer_strict = STRICT.with(|cell| cell.replace(strict));
+    let result = f();
+    STRICT.with(|cell| cell.set(outer_strict));
+    let inner = LOSSES
+        .with(|losses| losses.replace(outer))
+        .unwrap_or_default();
+    // Nested tracking also belongs to the enclosing evaluation.
+    LOSSES.with(|losses| {
+        if let Some(outer) = losses.borrow_mut().as_mut() {
+            outer.extend(inner.iter().cloned());
+        }
+    });
+    (result, inner)
+}
+
+/// Records that `operation` could not be computed exactly, or fails if only exact results are allowed.
+pub(crate) fn record(
+    operation: Operation,
+    operands: Vec<Token>,
+    reason: LossReason,
+) -> Result<(), MathError> {
+    if STRICT.with(Cell::get) {
+        return Err(MathError::Inexact(reason));
+    }
+    // Once a double is involved the loss has already been recorded.
+    if operands.iter().all(Token::is_exact) {
+        LOSSES.with(|losses| {
+            if let Some(losses) = losses.borrow_mut().as_mut() {
+                losses.push(PrecisionLoss {
+                    operation,
+                    operands,
+                    reason,
+                });
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Returns `value` as a double, recording why the exact operation could not be used.
+pub(crate) fn inexact(
+    operation: Operation,
+    operands: Vec<Token>,
+    reason: LossReason,
+    value: f64,
+) -> Result<Token, MathError> {
+    let value = double_check!(value);
+    record(operation, operands, reason)?;
+    Ok(Token::Basic(BasicToken::Double(value)))
+}
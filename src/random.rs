@@ -0,0 +1,83 @@
+//! The random numbers of `rand()`, `randint(a; b)`, `randfrac(maxden)` and `choice(list)`.
+//!
+//! Each [`crate::environment::Environment`] has a generator seeded from the clock, `seed(n)` or
+//! [`crate::environment::Environment::set_seed`] make the following numbers reproducible. The
+//! generator is SplitMix64, which is fast and good enough for practice problems but not for
+//! anything that has to be unpredictable.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A random number generator shared by an environment and the scopes made from it, so that a
+/// call to a user function continues the sequence of the session.
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: Arc<AtomicU64>,
+}
+
+impl Default for Random {
+    /// A generator seeded from the clock.
+    fn default() -> Random {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Random::new(time)
+    }
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Random {
+        Random {
+            state: Arc::new(AtomicU64::new(seed)),
+        }
+    }
+
+    /// A separate generator that continues with the same numbers as this one, without either of
+    /// them advancing the other.
+    pub fn snapshot(&self) -> Random {
+        Random::new(self.state.load(Ordering::Relaxed))
+    }
+
+    /// Restarts the sequence of this generator and every copy of it.
+    pub fn reseed(&self, seed: u64) {
+        self.state.store(seed, Ordering::Relaxed);
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        let mut z = self
+            .state
+            .fetch_add(GOLDEN_GAMMA, Ordering::Relaxed)
+            .wrapping_add(GOLDEN_GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_u128(&self) -> u128 {
+        ((self.next_u64() as u128) << 64) | self.next_u64() as u128
+    }
+
+    /// A number in `[0, 1)` with 53 random bits.
+    pub fn unit(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A whole number from `low` to `high`, both included, every one equally likely.
+    pub fn integer(&self, low: i128, high: i128) -> i128 {
+        let count = (high.wrapping_sub(low) as u128).wrapping_add(1);
+        if count == 0 {
+            return self.next_u128() as i128;
+        }
+        // Draws below 2^128 mod count are rejected so that the rest split evenly.
+        let threshold = count.wrapping_neg() % count;
+        loop {
+            let draw = self.next_u128();
+            if draw >= threshold {
+                return low.wrapping_add((draw % count) as i128);
+            }
+        }
+    }
+}
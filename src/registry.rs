@@ -0,0 +1,258 @@
+//! Named constants and functions that calculations can use.
+//!
+//! The built-in functions such as `sqrt` and `sin` are registered in [`Registry::default`] the
+//! same way an application registers its own:
+//!
+//! ```
+//! use calculator::environment::Environment;
+//! use calculator::expression::run;
+//! use calculator::number::mul;
+//! use calculator::registry::Arity;
+//! use calculator::types::{BasicToken, Token};
+//! use calculator::value::Value;
+//!
+//! let mut env = Environment::default();
+//! let registry = env.registry_mut();
+//! registry.register_constant("dozen", Token::Basic(BasicToken::Integer(12)));
+//! registry.register_function("double", Arity::Fixed(1), |args| {
+//!     mul(args[0].clone(), Token::Basic(BasicToken::Integer(2)))
+//! });
+//! assert_eq!(
+//!     run("double(dozen)", &mut env),
+//!     Ok(Some(Value::Number(Token::Basic(BasicToken::Integer(24)))))
+//! );
+//! ```
+
+use crate::number::{compare, exp};
+use crate::stats;
+use crate::trig_number::{acos, asin, atan, cos, sin, tan};
+use crate::types::{BasicToken, MathError, Token};
+use core::fmt;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// The exact implementation of a function.
+pub type ExactFn = dyn Fn(&[Token]) -> Result<Token, MathError> + Send + Sync;
+/// An optional `f64` implementation of a function used by [`crate::float`].
+pub type FloatFn = dyn Fn(&[f64]) -> Result<f64, MathError> + Send + Sync;
+
+/// How many arguments a function takes.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Arity {
+    Fixed(usize),
+    /// Any number of arguments, but at least one.
+    Variadic,
+}
+
+/// A function that can be called from a calculation.
+#[derive(Clone)]
+pub struct Builtin {
+    pub arity: Arity,
+    exact: Arc<ExactFn>,
+    float: Option<Arc<FloatFn>>,
+}
+
+impl Builtin {
+    pub fn call(&self, args: &[Token]) -> Result<Token, MathError> {
+        (self.exact)(args)
+    }
+
+    /// Calls the `f64` implementation, or the exact one with doubles if there is none.
+    pub fn call_float(&self, args: &[f64]) -> Result<f64, MathError> {
+        match &self.float {
+            Some(float) => float(args),
+            None => {
+                let args: Vec<Token> = args
+                    .iter()
+                    .map(|arg| Token::Basic(BasicToken::Double(*arg)))
+                    .collect();
+                Ok(self.call(&args)?.double())
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Builtin({:?})", self.arity)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    constants: BTreeMap<String, Token>,
+    functions: BTreeMap<String, Builtin>,
+}
+
+impl Registry {
+    /// A registry without even the built-in functions.
+    pub fn empty() -> Registry {
+        Registry {
+            constants: BTreeMap::new(),
+            functions: BTreeMap::new(),
+        }
+    }
+
+    pub fn register_constant(&mut self, name: &str, value: Token) {
+        self.constants.insert(name.to_string(), value);
+    }
+
+    /// Registers a function, replacing any function or `f64` implementation with the same name.
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        function: impl Fn(&[Token]) -> Result<Token, MathError> + Send + Sync + 'static,
+    ) {
+        let builtin = Builtin {
+            arity,
+            exact: Arc::new(function),
+            float: None,
+        };
+        self.functions.insert(name.to_string(), builtin);
+    }
+
+    /// Adds a faster `f64` implementation to an already registered function.
+    pub fn register_float(
+        &mut self,
+        name: &str,
+        function: impl Fn(&[f64]) -> Result<f64, MathError> + Send + Sync + 'static,
+    ) {
+        if let Some(builtin) = self.functions.get_mut(name) {
+            builtin.float = Some(Arc::new(function));
+        }
+    }
+
+    pub fn constant(&self, name: &str) -> Option<&Token> {
+        self.constants.get(name)
+    }
+
+    pub fn function(&self, name: &str) -> Option<&Builtin> {
+        self.functions.get(name)
+    }
+
+    /// Whether `name` is a registered constant or function, these cannot be assigned to.
+    pub fn contains(&self, name: &str) -> bool {
+        self.constants.contains_key(name) || self.functions.contains_key(name)
+    }
+
+    /// All registered constant and function names, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.constants
+            .keys()
+            .chain(self.functions.keys())
+            .map(String::as_str)
+    }
+}
+
+#[inline]
+fn check(value: f64) -> Result<f64, MathError> {
+    Ok(double_check!(value))
+}
+
+fn register_single(
+    registry: &mut Registry,
+    names: &[&str],
+    exact: fn(Token) -> Result<Token, MathError>,
+    float: fn(f64) -> Result<f64, MathError>,
+) {
+    for name in names {
+        registry.register_function(name, Arity::Fixed(1), move |args| exact(args[0].clone()));
+        registry.register_float(name, move |args| float(args[0]));
+    }
+}
+
+impl Default for Registry {
+    /// The built-in functions.
+    fn default() -> Registry {
+        let mut registry = Registry::empty();
+        register_single(
+            &mut registry,
+            &["sqrt"],
+            |arg| exp(arg, Token::Basic(BasicToken::fraction(0, 1, 2))),
+            |arg| match arg < 0.0 {
+                true => Err(MathError::ComplexNumber),
+                false => Ok(arg.sqrt()),
+            },
+        );
+        register_single(
+            &mut registry,
+            &["cbrt"],
+            |arg| exp(arg, Token::Basic(BasicToken::fraction(0, 1, 3))),
+            |arg| Ok(arg.cbrt()),
+        );
+        register_single(
+            &mut registry,
+            &["square"],
+            |arg| exp(arg, Token::Basic(BasicToken::Integer(2))),
+            |arg| check(arg * arg),
+        );
+        register_single(
+            &mut registry,
+            &["cube"],
+            |arg| exp(arg, Token::Basic(BasicToken::Integer(3))),
+            |arg| check(arg * arg * arg),
+        );
+        register_single(&mut registry, &["sin"], sin, |arg| Ok(arg.sin()));
+        register_single(&mut registry, &["cos"], cos, |arg| Ok(arg.cos()));
+        register_single(&mut registry, &["tan"], tan, |arg| check(arg.tan()));
+        register_single(
+            &mut registry,
+            &["asin", "arcsin", "arsin"],
+            asin,
+            |arg| match (-1.0..=1.0).contains(&arg) {
+                true => Ok(arg.asin()),
+                false => Err(MathError::DomainError),
+            },
+        );
+        register_single(
+            &mut registry,
+            &["acos", "arccos", "arcos"],
+            acos,
+            |arg| match (-1.0..=1.0).contains(&arg) {
+                true => Ok(arg.acos()),
+                false => Err(MathError::DomainError),
+            },
+        );
+        register_single(&mut registry, &["atan", "arctan", "artan"], atan, |arg| {
+            Ok(arg.atan())
+        });
+
+        registry.register_function("min", Arity::Variadic, |args| {
+            args.iter().try_fold(args[0].clone(), |min, arg| {
+                Ok(match compare(arg, &min)? {
+                    Ordering::Less => arg.clone(),
+                    _ => min,
+                })
+            })
+        });
+        registry.register_float("min", |args| {
+            Ok(args.iter().fold(f64::INFINITY, |a, b| a.min(*b)))
+        });
+        registry.register_function("max", Arity::Variadic, |args| {
+            args.iter().try_fold(args[0].clone(), |max, arg| {
+                Ok(match compare(arg, &max)? {
+                    Ordering::Greater => arg.clone(),
+                    _ => max,
+                })
+            })
+        });
+        registry.register_float("max", |args| {
+            Ok(args.iter().fold(f64::NEG_INFINITY, |a, b| a.max(*b)))
+        });
+
+        // `sum` is also a special form for series such as `sum(i^2; i; 1; 10)`.
+        registry.register_function("sum", Arity::Variadic, stats::sum);
+        registry.register_function("mean", Arity::Variadic, stats::mean);
+        registry.register_function("median", Arity::Variadic, stats::median);
+        registry.register_function("mode", Arity::Variadic, stats::mode);
+        registry.register_function("var", Arity::Variadic, stats::var);
+        registry.register_function("varp", Arity::Variadic, stats::varp);
+        registry.register_function("stdev", Arity::Variadic, stats::stdev);
+        registry.register_function("stdevp", Arity::Variadic, stats::stdevp);
+        registry.register_function("geomean", Arity::Variadic, stats::geomean);
+        registry.register_function("quantile", Arity::Variadic, stats::quantile);
+        registry
+    }
+}
@@ -0,0 +1,80 @@
+//! Closed forms of the finite sums and products `sum(expr; i; from; to)` and
+//! `prod(expr; i; from; to)`.
+//!
+//! Short series are evaluated term by term, these are only used once there are more than
+//! [`CLOSED_FORM_TERMS`] terms. Arithmetic and geometric sums use the usual formulas, products
+//! are only known for geometric terms.
+
+use crate::environment::Environment;
+use crate::symbolic::Expr;
+use crate::types::{BasicToken, MathError, Token};
+use crate::value::{self, Value};
+
+/// Series with more terms than this use a closed form if there is one.
+pub const CLOSED_FORM_TERMS: i128 = 1_000;
+/// Series longer than this fail with [`MathError::TooManyTerms`] without a closed form.
+pub const MAX_TERMS: i128 = 100_000;
+
+/// Whether the terms are added or multiplied.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Series {
+    Sum,
+    Product,
+}
+
+#[inline]
+fn int(value: i128) -> Value {
+    Value::Number(Token::Basic(BasicToken::Integer(value)))
+}
+
+/// The value of `term` when `index` is `i`.
+fn term_at(term: &Expr, index: &str, i: i128, env: &Environment) -> Result<Value, MathError> {
+    let mut scope = env.symbol_scope(index);
+    scope.set(index, int(i));
+    term.eval(&scope)
+}
+
+/// The sum or product of `term` for `index` from `from` to `to`, if a closed form is known.
+///
+/// `from` must not be larger than `to`.
+pub fn closed_form(
+    series: Series,
+    term: &Expr,
+    index: &str,
+    (from, to): (i128, i128),
+    env: &Environment,
+) -> Result<Option<Value>, MathError> {
+    let count = to
+        .checked_sub(from)
+        .and_then(|difference| difference.checked_add(1))
+        .ok_or(MathError::Overflow)?;
+    let first = || term_at(term, index, from, env);
+    let arithmetic = term
+        .derivative(index)
+        .is_ok_and(|slope| !slope.contains(index));
+    Ok(Some(match (series, term.ratio(index)) {
+        (Series::Sum, _) if arithmetic => {
+            let ends = value::add(first()?, term_at(term, index, to, env)?)?;
+            value::div(value::mul(int(count), ends)?, int(2))?
+        }
+        (Series::Sum, Some(ratio)) => {
+            let ratio = ratio.eval(env)?;
+            if ratio == int(1) {
+                return Ok(Some(value::mul(int(count), first()?)?));
+            }
+            let numerator = value::sub(int(1), value::exp(ratio.clone(), int(count))?)?;
+            let denominator = value::sub(int(1), ratio)?;
+            value::mul(first()?, value::div(numerator, denominator)?)?
+        }
+        // Every term is the first one times a power of the ratio, so the exponents add up to
+        // 0 + 1 + ... + (count - 1).
+        (Series::Product, Some(ratio)) => {
+            let exponent = count.checked_mul(count - 1).ok_or(MathError::Overflow)? / 2;
+            value::mul(
+                value::exp(first()?, int(count))?,
+                value::exp(ratio.eval(env)?, int(exponent))?,
+            )?
+        }
+        _ => return Ok(None),
+    }))
+}
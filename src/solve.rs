@@ -0,0 +1,339 @@
+//! Solves polynomial equations in one symbol exactly.
+//!
+//! Rational roots are found first by testing every candidate `p/q`, what is left is solved with
+//! the quadratic formula or Cardano's formula. Cubics are only solved when the result can be
+//! written with cube roots of rational numbers, otherwise [`MathError::NoClosedForm`] is returned.
+
+use crate::number::{add, compare, div, exp, mul, sub};
+use crate::polynomial::Polynomial;
+use crate::types::{BasicToken, Fraction, MathError, Token};
+use core::fmt;
+use gcd::Gcd;
+use std::cmp::Ordering;
+
+/// The largest constant or leading coefficient whose divisors are tried as rational roots.
+const MAX_SEARCH: i128 = 1_000_000_000_000;
+
+/// A real solution of an equation.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Root {
+    pub value: Token,
+    /// How many times the root is repeated, e.g. 2 for the root of `(x - 1)^2 = 0`.
+    pub multiplicity: u32,
+}
+
+/// Every solution of an equation in one symbol.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Solutions {
+    pub symbol: String,
+    /// The distinct real roots in ascending order.
+    pub roots: Vec<Root>,
+    /// How many roots are not real, counted with multiplicity.
+    pub complex: u32,
+    /// Every value of the symbol is a solution, as in `x + 1 = 1 + x`.
+    pub identity: bool,
+}
+
+impl fmt::Display for Solutions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.identity {
+            return write!(f, "Every {} is a solution", self.symbol);
+        }
+        match (self.roots.is_empty(), self.complex) {
+            (true, 0) => return write!(f, "No solution"),
+            (true, _) => return write!(f, "No real solutions"),
+            _ => {}
+        }
+        let roots: Vec<String> = self
+            .roots
+            .iter()
+            .map(|root| match root.multiplicity {
+                1 => format!("{} = {}", self.symbol, root.value),
+                2 => format!("{} = {} (double root)", self.symbol, root.value),
+                3 => format!("{} = {} (triple root)", self.symbol, root.value),
+                n => format!("{} = {} (repeated {} times)", self.symbol, root.value, n),
+            })
+            .collect();
+        write!(f, "{}", roots.join(", "))?;
+        match self.complex {
+            0 => Ok(()),
+            n => write!(f, " and {} complex roots", n),
+        }
+    }
+}
+
+#[inline]
+fn int(value: i128) -> Token {
+    Token::Basic(BasicToken::Integer(value))
+}
+
+/// Adds two values, skipping zeros so that no `Combined` is made with a zero in it.
+fn plus(lhs: Token, rhs: Token) -> Result<Token, MathError> {
+    match (lhs.is_zero(), rhs.is_zero()) {
+        (_, true) => Ok(lhs),
+        (true, _) => Ok(rhs),
+        _ => add(lhs, rhs),
+    }
+}
+
+/// The numerator and denominator of an integer or fraction.
+fn rational(token: &Token) -> Option<(i128, i128)> {
+    match token {
+        Token::Basic(BasicToken::Integer(i)) => Some((*i, 1)),
+        Token::Basic(BasicToken::Fraction(fr)) => {
+            Some((fr.int.checked_mul(fr.den)?.checked_add(fr.num)?, fr.den))
+        }
+        _ => None,
+    }
+}
+
+/// The coefficients multiplied by the lowest common denominator, if they are all rational.
+fn integer_coefficients(coefficients: &[Token]) -> Option<Vec<i128>> {
+    let rationals = coefficients
+        .iter()
+        .map(rational)
+        .collect::<Option<Vec<_>>>()?;
+    let mut lcm: i128 = 1;
+    for (_, den) in &rationals {
+        let gcd = (lcm as u128).gcd(*den as u128) as i128;
+        lcm = (lcm / gcd).checked_mul(*den)?;
+    }
+    rationals
+        .iter()
+        .map(|(num, den)| num.checked_mul(lcm / den))
+        .collect()
+}
+
+/// The positive divisors of `n`, or `None` if there are too many to try.
+fn divisors(n: i128) -> Option<Vec<i128>> {
+    let n = n.checked_abs()?;
+    if n > MAX_SEARCH {
+        return None;
+    }
+    let (mut small, mut large) = (vec![], vec![]);
+    let mut divisor = 1;
+    while divisor * divisor <= n {
+        if n % divisor == 0 {
+            small.push(divisor);
+            if divisor != n / divisor {
+                large.push(n / divisor);
+            }
+        }
+        divisor += 1;
+    }
+    small.extend(large.into_iter().rev());
+    Some(small)
+}
+
+/// `q^n` times the value of the polynomial at `p/q`, which is zero if `p/q` is a root.
+fn scaled_value(coefficients: &[i128], p: i128, q: i128) -> Option<i128> {
+    let mut value = *coefficients.last()?;
+    let mut q_power: i128 = 1;
+    for coefficient in coefficients.iter().rev().skip(1) {
+        q_power = q_power.checked_mul(q)?;
+        value = value
+            .checked_mul(p)?
+            .checked_add(coefficient.checked_mul(q_power)?)?;
+    }
+    Some(value)
+}
+
+/// Divides the polynomial by `qx - p`, which must be a factor of it.
+fn deflate(coefficients: &[i128], p: i128, q: i128) -> Option<Vec<i128>> {
+    let degree = coefficients.len() - 1;
+    let mut quotient = vec![0; degree];
+    let mut carry = 0;
+    for power in (1..=degree).rev() {
+        quotient[power - 1] = coefficients[power].checked_add(carry)? / q;
+        carry = quotient[power - 1].checked_mul(p)?;
+    }
+    Some(quotient)
+}
+
+/// Moves every rational root into `roots`, returning the coefficients of what is left.
+fn rational_roots(mut coefficients: Vec<i128>, roots: &mut Vec<Token>) -> Vec<i128> {
+    'search: while coefficients.len() > 1 {
+        let (constants, leading) = match (
+            divisors(coefficients[0]),
+            divisors(*coefficients.last().unwrap()),
+        ) {
+            (Some(constants), Some(leading)) => (constants, leading),
+            _ => break,
+        };
+        for p in &constants {
+            for q in leading
+                .iter()
+                .filter(|q| (*p as u128).gcd(**q as u128) == 1)
+            {
+                for p in [*p, -p] {
+                    if scaled_value(&coefficients, p, *q) != Some(0) {
+                        continue;
+                    }
+                    if let (Some(quotient), Ok(root)) = (
+                        deflate(&coefficients, p, *q),
+                        Fraction::new(0, p, *q).normalise(),
+                    ) {
+                        coefficients = quotient;
+                        roots.push(Token::Basic(root));
+                        continue 'search;
+                    }
+                }
+            }
+        }
+        break;
+    }
+    coefficients
+}
+
+/// The real roots of `ax + b = 0`.
+fn linear(b: &Token, a: &Token) -> Result<Vec<Token>, MathError> {
+    Ok(vec![div(b.clone().negate()?, a.clone())?])
+}
+
+/// The real roots of `ax^2 + bx + c = 0`, and the number of complex ones.
+fn quadratic(c: &Token, b: &Token, a: &Token) -> Result<(Vec<Token>, u32), MathError> {
+    let discriminant = sub(
+        mul(b.clone(), b.clone())?,
+        mul(mul(int(4), a.clone())?, c.clone())?,
+    )?;
+    let denominator = mul(int(2), a.clone())?;
+    let negated = b.clone().negate()?;
+    if discriminant.is_zero() {
+        let root = div(negated, denominator)?;
+        return Ok((vec![root.clone(), root], 0));
+    }
+    if compare(&discriminant, &int(0))? == Ordering::Less {
+        return Ok((vec![], 2));
+    }
+    let root = exp(discriminant, Token::Basic(BasicToken::fraction(0, 1, 2)))?;
+    Ok((
+        vec![
+            div(plus(negated.clone(), root.clone())?, denominator.clone())?,
+            div(plus(negated, root.negate()?)?, denominator)?,
+        ],
+        0,
+    ))
+}
+
+/// The real roots of `ax^3 + bx^2 + cx + d = 0` using Cardano's formula, and the number of
+/// complex ones.
+fn cubic(d: &Token, c: &Token, b: &Token, a: &Token) -> Result<(Vec<Token>, u32), MathError> {
+    // Substituting x = t - b/3a gives t^3 + pt + q = 0.
+    let shift = div(b.clone(), mul(int(3), a.clone())?)?;
+    let a_squared = mul(a.clone(), a.clone())?;
+    let p = div(
+        sub(
+            mul(mul(int(3), a.clone())?, c.clone())?,
+            mul(b.clone(), b.clone())?,
+        )?,
+        mul(int(3), a_squared.clone())?,
+    )?;
+    let q = div(
+        add(
+            sub(
+                mul(int(2), exp(b.clone(), int(3))?)?,
+                mul(mul(mul(int(9), a.clone())?, b.clone())?, c.clone())?,
+            )?,
+            mul(mul(int(27), a_squared)?, d.clone())?,
+        )?,
+        mul(int(27), exp(a.clone(), int(3))?)?,
+    )?;
+    let discriminant = add(
+        div(mul(q.clone(), q.clone())?, int(4))?,
+        div(exp(p.clone(), int(3))?, int(27))?,
+    )?;
+    let roots = if discriminant.is_zero() {
+        if p.is_zero() {
+            vec![int(0); 3]
+        } else {
+            let single = div(mul(int(3), q.clone())?, p.clone())?;
+            let double = div(mul(int(-3), q)?, mul(int(2), p)?)?;
+            vec![single, double.clone(), double]
+        }
+    } else if compare(&discriminant, &int(0))? == Ordering::Less {
+        // Three irrational real roots, these need cube roots of complex numbers.
+        return Err(MathError::NoClosedForm);
+    } else {
+        let root = exp(discriminant, Token::Basic(BasicToken::fraction(0, 1, 2)))?;
+        if rational(&root).is_none() {
+            return Err(MathError::NoClosedForm);
+        }
+        let half = div(q, int(-2))?;
+        let third = Token::Basic(BasicToken::fraction(0, 1, 3));
+        let u = exp(add(half.clone(), root.clone())?, third.clone())?;
+        let v = exp(sub(half, root)?, third)?;
+        if !u.is_exact() || !v.is_exact() {
+            return Err(MathError::NoClosedForm);
+        }
+        vec![plus(u, v)?]
+    };
+    let complex = 3 - roots.len() as u32;
+    let roots = roots
+        .into_iter()
+        .map(|root| plus(root, shift.clone().negate()?))
+        .collect::<Result<_, _>>()?;
+    Ok((roots, complex))
+}
+
+/// Solves `polynomial = 0` for `symbol`.
+///
+/// Every other symbol must have a value, the coefficients of `symbol` have to be numbers.
+pub fn solve(polynomial: &Polynomial, symbol: &str) -> Result<Solutions, MathError> {
+    let mut coefficients = polynomial
+        .coefficients(symbol)?
+        .iter()
+        .map(Polynomial::as_constant)
+        .collect::<Option<Vec<Token>>>()
+        .ok_or(MathError::NoClosedForm)?;
+    let mut solutions = Solutions {
+        symbol: symbol.to_string(),
+        roots: vec![],
+        complex: 0,
+        identity: false,
+    };
+    if coefficients.iter().all(Token::is_zero) {
+        solutions.identity = true;
+        return Ok(solutions);
+    }
+
+    let zeros = coefficients.iter().take_while(|c| c.is_zero()).count();
+    let mut roots = vec![int(0); zeros];
+    coefficients.drain(..zeros);
+    if let Some(integers) = integer_coefficients(&coefficients) {
+        coefficients = rational_roots(integers, &mut roots)
+            .into_iter()
+            .map(int)
+            .collect();
+    }
+    match coefficients.as_slice() {
+        [_] => {}
+        [b, a] => roots.extend(linear(b, a)?),
+        [c, b, a] => {
+            let (real, complex) = quadratic(c, b, a)?;
+            roots.extend(real);
+            solutions.complex = complex;
+        }
+        [d, c, b, a] => {
+            let (real, complex) = cubic(d, c, b, a)?;
+            roots.extend(real);
+            solutions.complex = complex;
+        }
+        _ => return Err(MathError::NoClosedForm),
+    }
+
+    roots.sort_by(|a, b| {
+        a.double()
+            .partial_cmp(&b.double())
+            .unwrap_or(Ordering::Equal)
+    });
+    for root in roots {
+        match solutions.roots.last_mut() {
+            Some(last) if last.value == root => last.multiplicity += 1,
+            _ => solutions.roots.push(Root {
+                value: root,
+                multiplicity: 1,
+            }),
+        }
+    }
+    Ok(solutions)
+}
@@ -0,0 +1,158 @@
+//! Statistics over the arguments of a function, such as `mean(1; 2; 4)`.
+//!
+//! The values keep their exact types, so the mean of fractions is a fraction and the standard
+//! deviation is a radical when the variance is not a square.
+
+use crate::number::{add, compare, div, exp, mul, sub};
+use crate::types::{BasicToken, MathError, Token};
+use std::cmp::Ordering;
+
+#[inline]
+fn int(value: i128) -> Token {
+    Token::Basic(BasicToken::Integer(value))
+}
+
+#[inline]
+fn count(values: &[Token]) -> Token {
+    int(values.len() as i128)
+}
+
+/// The values from smallest to largest.
+fn sorted(values: &[Token]) -> Result<Vec<Token>, MathError> {
+    let mut sorted = values.to_vec();
+    let mut error = None;
+    sorted.sort_by(|a, b| {
+        compare(a, b).unwrap_or_else(|kind| {
+            error.get_or_insert(kind);
+            Ordering::Equal
+        })
+    });
+    match error {
+        Some(kind) => Err(kind),
+        None => Ok(sorted),
+    }
+}
+
+/// Checks that there are at least `expected` values, e.g. two for the sample variance.
+fn at_least(values: &[Token], expected: usize) -> Result<(), MathError> {
+    match values.len() >= expected {
+        true => Ok(()),
+        false => Err(MathError::ArgumentCount {
+            expected,
+            found: values.len(),
+        }),
+    }
+}
+
+/// The largest integer not above `token`.
+fn floor(token: &Token) -> i128 {
+    match token {
+        Token::Basic(BasicToken::Integer(i)) => *i,
+        Token::Basic(BasicToken::Fraction(fraction)) => fraction.int - (fraction.num < 0) as i128,
+        _ => token.double().floor() as i128,
+    }
+}
+
+pub fn sum(values: &[Token]) -> Result<Token, MathError> {
+    values
+        .iter()
+        .try_fold(int(0), |sum, value| add(sum, value.clone()))
+}
+
+pub fn mean(values: &[Token]) -> Result<Token, MathError> {
+    div(sum(values)?, count(values))
+}
+
+/// The middle value, or the mean of the two middle values if there is an even number of them.
+pub fn median(values: &[Token]) -> Result<Token, MathError> {
+    let sorted = sorted(values)?;
+    let middle = sorted.len() / 2;
+    match sorted.len() % 2 {
+        1 => Ok(sorted[middle].clone()),
+        _ => mean(&sorted[middle - 1..=middle]),
+    }
+}
+
+/// The most common value, the smallest one if several are equally common.
+pub fn mode(values: &[Token]) -> Result<Token, MathError> {
+    let sorted = sorted(values)?;
+    let mut best = (&sorted[0], 0);
+    let mut start = 0;
+    for end in 1..=sorted.len() {
+        if end == sorted.len() || sorted[end] != sorted[start] {
+            if end - start > best.1 {
+                best = (&sorted[start], end - start);
+            }
+            start = end;
+        }
+    }
+    Ok(best.0.clone())
+}
+
+/// The sum of the squared differences to the mean.
+fn squared_deviations(values: &[Token]) -> Result<Token, MathError> {
+    let mean = mean(values)?;
+    values.iter().try_fold(int(0), |sum, value| {
+        let deviation = sub(value.clone(), mean.clone())?;
+        add(sum, mul(deviation.clone(), deviation)?)
+    })
+}
+
+/// The sample variance, which divides by one less than the number of values.
+pub fn var(values: &[Token]) -> Result<Token, MathError> {
+    at_least(values, 2)?;
+    div(squared_deviations(values)?, int(values.len() as i128 - 1))
+}
+
+/// The population variance.
+pub fn varp(values: &[Token]) -> Result<Token, MathError> {
+    div(squared_deviations(values)?, count(values))
+}
+
+/// The sample standard deviation.
+pub fn stdev(values: &[Token]) -> Result<Token, MathError> {
+    exp(var(values)?, Token::Basic(BasicToken::fraction(0, 1, 2)))
+}
+
+/// The population standard deviation.
+pub fn stdevp(values: &[Token]) -> Result<Token, MathError> {
+    exp(varp(values)?, Token::Basic(BasicToken::fraction(0, 1, 2)))
+}
+
+/// The `n`th root of the product of the `n` values, which all have to be positive.
+pub fn geomean(values: &[Token]) -> Result<Token, MathError> {
+    if values.iter().any(|value| value.double() <= 0.0) {
+        return Err(MathError::DomainError);
+    }
+    let product = values
+        .iter()
+        .try_fold(int(1), |product, value| mul(product, value.clone()))?;
+    match values.len() {
+        1 => Ok(product),
+        n => exp(product, Token::Basic(BasicToken::fraction(0, 1, n as i128))),
+    }
+}
+
+/// The value below which the fraction `p` of the values lie, where `p` is the last argument.
+///
+/// Between two values the result is interpolated linearly, so `quantile(1; 2; 3; 4; 1/2)` is
+/// the median 5/2.
+pub fn quantile(args: &[Token]) -> Result<Token, MathError> {
+    at_least(args, 2)?;
+    let (p, values) = args.split_last().unwrap();
+    if !(0.0..=1.0).contains(&p.double()) {
+        return Err(MathError::DomainError);
+    }
+    let sorted = sorted(values)?;
+    // Where p lies between the smallest value at 0 and the largest at n - 1.
+    let position = mul(int(sorted.len() as i128 - 1), p.clone())?;
+    let below = floor(&position).clamp(0, sorted.len() as i128 - 1) as usize;
+    let offset = sub(position, int(below as i128))?;
+    match sorted.get(below + 1) {
+        Some(above) if !offset.is_zero() => {
+            let step = sub(above.clone(), sorted[below].clone())?;
+            add(sorted[below].clone(), mul(offset, step)?)
+        }
+        _ => Ok(sorted[below].clone()),
+    }
+}
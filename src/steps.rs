@@ -0,0 +1,201 @@
+use crate::types::{BasicToken, MathError, Token};
+use core::fmt;
+use std::cell::RefCell;
+
+/// One rewrite made while simplifying a calculation.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Step {
+    /// An operation between two exact values, e.g. `1/2 + 1/3 = 5/6`.
+    Arithmetic {
+        operator: char,
+        lhs: Token,
+        rhs: Token,
+        result: Token,
+    },
+    /// Two roots with the same base were added or subtracted, e.g. `2√2 + 3√2 = 5√2`.
+    CombineRadicals {
+        operator: char,
+        lhs: Token,
+        rhs: Token,
+        result: Token,
+    },
+    /// Factors were taken out of a root, e.g. `√72 = 6√2`.
+    SimplifyRoot {
+        cube: bool,
+        base: i128,
+        outside: i128,
+        inside: i128,
+    },
+    /// A trig function was looked up in the table of exact values, e.g. `sin(π/6) = 1/2`.
+    TrigValue {
+        function: &'static str,
+        argument: Token,
+        result: Token,
+    },
+}
+
+/// Writes an operand of an operation, in brackets if it is a sum such as `√2 + 1`.
+fn operand(token: &Token) -> String {
+    match token {
+        Token::Combined(_) => format!("({})", token),
+        _ => token.to_string(),
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Arithmetic {
+                operator,
+                lhs,
+                rhs,
+                result,
+            } => write!(
+                f,
+                "{} {} {} = {}",
+                operand(lhs),
+                operator,
+                operand(rhs),
+                result
+            ),
+            Step::CombineRadicals {
+                operator,
+                lhs,
+                rhs,
+                result,
+            } => write!(
+                f,
+                "Combine like radicals: {} {} {} = {}",
+                operand(lhs),
+                operator,
+                operand(rhs),
+                result
+            ),
+            Step::SimplifyRoot {
+                cube,
+                base,
+                outside,
+                inside,
+            } => {
+                let root = if *cube { '∛' } else { '√' };
+                match inside {
+                    1 => write!(f, "{}{} = {}", root, base, outside),
+                    _ => write!(f, "{}{} = {}{}{}", root, base, outside, root, inside),
+                }
+            }
+            Step::TrigValue {
+                function,
+                argument,
+                result,
+            } => write!(f, "{}({}) = {}", function, argument, result),
+        }
+    }
+}
+
+thread_local! {
+    static STEPS: RefCell<Option<Vec<Step>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f` and returns the steps it took, in order.
+pub fn record_steps<T>(f: impl FnOnce() -> T) -> (T, Vec<Step>) {
+    let outer = STEPS.with(|steps| steps.replace(Some(vec![])));
+    let result = f();
+    let inner = STEPS.with(|steps| steps.replace(outer)).unwrap_or_default();
+    STEPS.with(|steps| {
+        if let Some(outer) = steps.borrow_mut().as_mut() {
+            outer.extend(inner.iter().cloned());
+        }
+    });
+    (result, inner)
+}
+
+#[inline]
+fn recording() -> bool {
+    STEPS.with(|steps| steps.borrow().is_some())
+}
+
+/// Adds a step if steps are being recorded, `step` is only called when they are.
+pub(crate) fn record(step: impl FnOnce() -> Step) {
+    STEPS.with(|steps| {
+        if let Some(steps) = steps.borrow_mut().as_mut() {
+            steps.push(step());
+        }
+    });
+}
+
+fn like_radicals(lhs: &Token, rhs: &Token) -> bool {
+    match (lhs, rhs) {
+        (Token::Basic(l), Token::Basic(r)) | (Token::Pi(l), Token::Pi(r)) => match (l, r) {
+            (
+                BasicToken::SIntRoot(_) | BasicToken::SFracRoot(_),
+                BasicToken::SIntRoot(_) | BasicToken::SFracRoot(_),
+            )
+            | (
+                BasicToken::CIntRoot(_) | BasicToken::CFracRoot(_),
+                BasicToken::CIntRoot(_) | BasicToken::CFracRoot(_),
+            ) => root_base(l) == root_base(r),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn root_base(token: &BasicToken) -> Option<i128> {
+    match token {
+        BasicToken::SIntRoot(root) => Some(root.base),
+        BasicToken::SFracRoot(root) => Some(root.base),
+        BasicToken::CIntRoot(root) => Some(root.base),
+        BasicToken::CFracRoot(root) => Some(root.base),
+        _ => None,
+    }
+}
+
+/// Applies `operation` and records it as a step if both the operands and result are exact.
+pub(crate) fn operation(
+    operator: char,
+    lhs: Token,
+    rhs: Token,
+    operation: fn(Token, Token) -> Result<Token, MathError>,
+) -> Result<Token, MathError> {
+    if !recording() || !lhs.is_exact() || !rhs.is_exact() {
+        return operation(lhs, rhs);
+    }
+    let result = operation(lhs.clone(), rhs.clone())?;
+    if result.is_exact() {
+        record(|| match (operator, like_radicals(&lhs, &rhs)) {
+            ('+' | '-', true) => Step::CombineRadicals {
+                operator,
+                lhs,
+                rhs,
+                result: result.clone(),
+            },
+            _ => Step::Arithmetic {
+                operator,
+                lhs,
+                rhs,
+                result: result.clone(),
+            },
+        });
+    }
+    Ok(result)
+}
+
+/// Applies a trig `function` and records it as a step if the result came from the exact tables.
+pub(crate) fn trig_value(
+    function: &'static str,
+    argument: Token,
+    value: fn(Token) -> Result<Token, MathError>,
+) -> Result<Token, MathError> {
+    if !recording() || !argument.is_exact() {
+        return value(argument);
+    }
+    let result = value(argument.clone())?;
+    if result.is_exact() {
+        record(|| Step::TrigValue {
+            function,
+            argument,
+            result: result.clone(),
+        });
+    }
+    Ok(result)
+}
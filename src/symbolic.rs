@@ -0,0 +1,763 @@
+//! Expressions that are kept as a tree of operations, such as the derivative `cos(x)` of
+//! `sin(x)`, which is neither a number nor a polynomial.
+//!
+//! Expressions are built from the parsed input with every variable except the symbol replaced by
+//! its value and user functions inlined. The constructors such as [`sum`] fold numbers with the
+//! usual exact arithmetic and remove trivial operations like `0 + a` and `1*a`.
+
+use crate::environment::{Environment, MAX_CALL_DEPTH};
+use crate::expression::{
+    answer_eval, body_error, call_eval, error_at, is_special_call, number_eval, resolve, Callee,
+    Expression, Rule, PREC_CLIMBER,
+};
+use crate::number;
+use crate::polynomial::Polynomial;
+use crate::types::{BasicToken, EvalError, MathError, Token};
+use crate::value::{self, Value};
+use core::fmt;
+use pest::iterators::{Pair, Pairs};
+use pest::Parser;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expr {
+    Number(Token),
+    Symbol(String),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+    /// A call to a registered function such as `sin`.
+    Call(String, Vec<Expr>),
+}
+
+#[inline]
+fn int(value: i128) -> Expr {
+    Expr::Number(Token::Basic(BasicToken::Integer(value)))
+}
+
+#[inline]
+fn zero() -> Token {
+    Token::Basic(BasicToken::Integer(0))
+}
+
+impl Expr {
+    fn is_zero(&self) -> bool {
+        matches!(self, Expr::Number(token) if token.is_zero())
+    }
+
+    fn is_one(&self) -> bool {
+        self == &int(1)
+    }
+
+    /// Whether `symbol` appears anywhere in the expression.
+    pub fn contains(&self, symbol: &str) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Symbol(name) => name == symbol,
+            Expr::Add(lhs, rhs)
+            | Expr::Sub(lhs, rhs)
+            | Expr::Mul(lhs, rhs)
+            | Expr::Div(lhs, rhs)
+            | Expr::Pow(lhs, rhs) => lhs.contains(symbol) || rhs.contains(symbol),
+            Expr::Call(_, args) => args.iter().any(|arg| arg.contains(symbol)),
+        }
+    }
+
+    /// Evaluates the expression with the variables and functions of `env`, symbols without a
+    /// value stay symbolic.
+    pub fn eval(&self, env: &Environment) -> Result<Value, MathError> {
+        match self {
+            Expr::Number(token) => Ok(Value::Number(token.clone())),
+            Expr::Symbol(name) => Ok(match env.get(name) {
+                Some(value) => value.clone(),
+                None => Value::Polynomial(Polynomial::symbol(name)),
+            }),
+            Expr::Add(lhs, rhs) => value::add(lhs.eval(env)?, rhs.eval(env)?),
+            Expr::Sub(lhs, rhs) => value::sub(lhs.eval(env)?, rhs.eval(env)?),
+            Expr::Mul(lhs, rhs) => value::mul(lhs.eval(env)?, rhs.eval(env)?),
+            Expr::Div(lhs, rhs) => value::div(lhs.eval(env)?, rhs.eval(env)?),
+            Expr::Pow(lhs, rhs) => value::exp(lhs.eval(env)?, rhs.eval(env)?),
+            Expr::Call(name, args) => {
+                let builtin = env
+                    .registry()
+                    .function(name)
+                    .ok_or(MathError::UndefinedFunction)?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.eval(env)?.number())
+                    .collect::<Result<Vec<_>, _>>()?;
+                builtin.call(&args).map(Value::Number)
+            }
+        }
+    }
+
+    /// The expression as a number or polynomial if it is one, e.g. `2x*3` becomes `6x`.
+    pub fn simplify(self, env: &Environment) -> Value {
+        match self.eval(env) {
+            Ok(Value::Number(token)) => Value::Number(token),
+            Ok(Value::Polynomial(polynomial)) => Value::Polynomial(polynomial),
+            _ => Value::Expression(self),
+        }
+    }
+
+    /// The derivative with respect to `symbol`.
+    pub fn derivative(&self, symbol: &str) -> Result<Expr, MathError> {
+        if !self.contains(symbol) {
+            return Ok(int(0));
+        }
+        match self {
+            Expr::Number(_) => Ok(int(0)),
+            Expr::Symbol(_) => Ok(int(1)),
+            Expr::Add(lhs, rhs) => sum(lhs.derivative(symbol)?, rhs.derivative(symbol)?),
+            Expr::Sub(lhs, rhs) => difference(lhs.derivative(symbol)?, rhs.derivative(symbol)?),
+            Expr::Mul(lhs, rhs) => sum(
+                product(lhs.derivative(symbol)?, *rhs.clone())?,
+                product(*lhs.clone(), rhs.derivative(symbol)?)?,
+            ),
+            Expr::Div(lhs, rhs) => quotient(
+                difference(
+                    product(lhs.derivative(symbol)?, *rhs.clone())?,
+                    product(*lhs.clone(), rhs.derivative(symbol)?)?,
+                )?,
+                power(*rhs.clone(), int(2))?,
+            ),
+            // Exponents containing the symbol would need logarithms.
+            Expr::Pow(_, exponent) if exponent.contains(symbol) => Err(MathError::NoDerivative),
+            Expr::Pow(base, exponent) => product(
+                product(
+                    *exponent.clone(),
+                    power(*base.clone(), difference(*exponent.clone(), int(1))?)?,
+                )?,
+                base.derivative(symbol)?,
+            ),
+            Expr::Call(name, args) => match args.as_slice() {
+                [arg] => product(arg.derivative(symbol)?, outer_derivative(name, arg)?),
+                _ => Err(MathError::NoDerivative),
+            },
+        }
+    }
+
+    /// An antiderivative with respect to `symbol`.
+    ///
+    /// Only sums of constant multiples of powers, `sin`, `cos` and roots of expressions linear in
+    /// `symbol` are supported, anything else is [`MathError::NoClosedForm`].
+    pub fn antiderivative(&self, symbol: &str) -> Result<Expr, MathError> {
+        if !self.contains(symbol) {
+            return product(self.clone(), Expr::Symbol(symbol.to_string()));
+        }
+        match self {
+            Expr::Symbol(_) => power_antiderivative(self, &int(1), symbol),
+            Expr::Add(lhs, rhs) => sum(lhs.antiderivative(symbol)?, rhs.antiderivative(symbol)?),
+            Expr::Sub(lhs, rhs) => {
+                difference(lhs.antiderivative(symbol)?, rhs.antiderivative(symbol)?)
+            }
+            Expr::Mul(lhs, rhs) if !lhs.contains(symbol) => {
+                product(*lhs.clone(), rhs.antiderivative(symbol)?)
+            }
+            Expr::Mul(lhs, rhs) if !rhs.contains(symbol) => {
+                product(lhs.antiderivative(symbol)?, *rhs.clone())
+            }
+            Expr::Div(lhs, rhs) if !rhs.contains(symbol) => {
+                quotient(lhs.antiderivative(symbol)?, *rhs.clone())
+            }
+            Expr::Div(lhs, rhs) if !lhs.contains(symbol) => product(
+                *lhs.clone(),
+                power(*rhs.clone(), int(-1))?.antiderivative(symbol)?,
+            ),
+            Expr::Pow(base, exponent) if !exponent.contains(symbol) => {
+                power_antiderivative(base, exponent, symbol)
+            }
+            Expr::Call(name, args) => match args.as_slice() {
+                [arg] => quotient(outer_antiderivative(name, arg)?, slope(arg, symbol)?),
+                _ => Err(MathError::NoClosedForm),
+            },
+            _ => Err(MathError::NoClosedForm),
+        }
+    }
+
+    /// Checks that the expression is real and finite for every value of `symbol` from `a` to
+    /// `b`, so that an antiderivative may be evaluated at the ends of the interval.
+    ///
+    /// Divisors and the bases of powers and roots have to be linear in `symbol`, as they are in
+    /// antiderivatives, so that their sign is known from the ends. One that reaches zero as a
+    /// divisor is [`MathError::Divergent`], one that goes below zero under an even root is
+    /// [`MathError::ComplexNumber`]. Anything else that restricts `symbol` is
+    /// [`MathError::NoClosedForm`].
+    pub fn check_between(
+        &self,
+        symbol: &str,
+        (a, b): (&Token, &Token),
+        env: &Environment,
+    ) -> Result<(), MathError> {
+        if !self.contains(symbol) {
+            return Ok(());
+        }
+        let signs = |base: &Expr| -> Result<[Ordering; 2], MathError> {
+            if base.contains(symbol) {
+                slope(base, symbol)?;
+            }
+            let mut scope = env.clone();
+            let mut signs = [Ordering::Equal; 2];
+            for (sign, end) in signs.iter_mut().zip([a, b]) {
+                scope.set(symbol, Value::Number(end.clone()));
+                *sign = number::compare(&base.eval(&scope)?.number()?, &zero())?;
+            }
+            Ok(signs)
+        };
+        let non_zero = |base: &Expr| match signs(base)? {
+            [Ordering::Less, Ordering::Less] | [Ordering::Greater, Ordering::Greater] => Ok(()),
+            _ => Err(MathError::Divergent),
+        };
+        let non_negative = |base: &Expr| match signs(base)?.contains(&Ordering::Less) {
+            true => Err(MathError::ComplexNumber),
+            false => Ok(()),
+        };
+        let check = |expr: &Expr| expr.check_between(symbol, (a, b), env);
+        match self {
+            Expr::Number(_) | Expr::Symbol(_) => Ok(()),
+            Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) | Expr::Mul(lhs, rhs) => {
+                check(lhs)?;
+                check(rhs)
+            }
+            Expr::Div(lhs, rhs) => {
+                check(lhs)?;
+                check(rhs)?;
+                non_zero(rhs)
+            }
+            Expr::Pow(base, exponent) => {
+                check(base)?;
+                let exponent = match &**exponent {
+                    Expr::Number(exponent) => exponent,
+                    _ => return Err(MathError::NoClosedForm),
+                };
+                match exponent {
+                    Token::Basic(BasicToken::Integer(_)) => {}
+                    Token::Basic(BasicToken::Fraction(fraction)) if fraction.den % 2 != 0 => {}
+                    Token::Basic(BasicToken::Fraction(_)) => non_negative(base)?,
+                    _ => return Err(MathError::NoClosedForm),
+                }
+                match number::compare(exponent, &zero())? {
+                    Ordering::Less => non_zero(base),
+                    _ => Ok(()),
+                }
+            }
+            Expr::Call(name, args) => {
+                for arg in args {
+                    check(arg)?;
+                }
+                match (name.as_str(), args.as_slice()) {
+                    ("sin" | "cos" | "cbrt", _) => Ok(()),
+                    ("sqrt", [arg]) => non_negative(arg),
+                    _ => Err(MathError::NoClosedForm),
+                }
+            }
+        }
+    }
+
+    /// The ratio between the values at `symbol + 1` and `symbol` if the expression is geometric
+    /// in `symbol`, i.e. a constant times powers with exponents linear in `symbol`.
+    pub fn ratio(&self, symbol: &str) -> Option<Expr> {
+        match self {
+            _ if !self.contains(symbol) => Some(int(1)),
+            Expr::Pow(base, exponent) if !base.contains(symbol) => {
+                power(*base.clone(), slope(exponent, symbol).ok()?).ok()
+            }
+            Expr::Mul(lhs, rhs) => product(lhs.ratio(symbol)?, rhs.ratio(symbol)?).ok(),
+            Expr::Div(lhs, rhs) => quotient(lhs.ratio(symbol)?, rhs.ratio(symbol)?).ok(),
+            _ => None,
+        }
+    }
+
+    /// How tightly the expression binds when written out, used to place brackets.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Add(..) | Expr::Sub(..) => 1,
+            Expr::Mul(..) | Expr::Div(..) => 2,
+            Expr::Number(Token::Basic(BasicToken::Integer(i))) if *i < 0 => 2,
+            Expr::Pow(..) => 3,
+            Expr::Number(_) | Expr::Symbol(_) | Expr::Call(..) => 4,
+        }
+    }
+}
+
+/// The derivative of the function `name` at `arg`, which still has to be multiplied by the
+/// derivative of `arg`.
+fn outer_derivative(name: &str, arg: &Expr) -> Result<Expr, MathError> {
+    let call = |name: &str, arg: Expr| Expr::Call(name.to_string(), vec![arg]);
+    let one_minus_square = || difference(int(1), power(arg.clone(), int(2))?);
+    match name {
+        "sin" => Ok(call("cos", arg.clone())),
+        "cos" => product(int(-1), call("sin", arg.clone())),
+        "tan" => quotient(int(1), power(call("cos", arg.clone()), int(2))?),
+        "asin" | "arcsin" | "arsin" => quotient(int(1), call("sqrt", one_minus_square()?)),
+        "acos" | "arccos" | "arcos" => quotient(int(-1), call("sqrt", one_minus_square()?)),
+        "atan" | "arctan" | "artan" => quotient(int(1), sum(int(1), power(arg.clone(), int(2))?)?),
+        "sqrt" => quotient(int(1), product(int(2), call("sqrt", arg.clone()))?),
+        "cbrt" => quotient(
+            int(1),
+            product(int(3), power(call("cbrt", arg.clone()), int(2))?)?,
+        ),
+        "square" => product(int(2), arg.clone()),
+        "cube" => product(int(3), power(arg.clone(), int(2))?),
+        _ => Err(MathError::NoDerivative),
+    }
+}
+
+/// The derivative of `arg` if it is constant, meaning that `arg` is linear in `symbol`.
+fn slope(arg: &Expr, symbol: &str) -> Result<Expr, MathError> {
+    match arg.derivative(symbol) {
+        Ok(slope) if !slope.contains(symbol) && !slope.is_zero() => Ok(slope),
+        _ => Err(MathError::NoClosedForm),
+    }
+}
+
+/// The antiderivative of `base^exponent` where `base` is linear in `symbol`.
+fn power_antiderivative(base: &Expr, exponent: &Expr, symbol: &str) -> Result<Expr, MathError> {
+    let raised = sum(exponent.clone(), int(1))?;
+    // The antiderivative of 1/x would need logarithms.
+    if raised.is_zero() {
+        return Err(MathError::NoClosedForm);
+    }
+    quotient(
+        power(base.clone(), raised.clone())?,
+        product(raised, slope(base, symbol)?)?,
+    )
+}
+
+/// The antiderivative of the function `name` at `arg`, which still has to be divided by the
+/// slope of `arg`.
+fn outer_antiderivative(name: &str, arg: &Expr) -> Result<Expr, MathError> {
+    let call = |name: &str| Expr::Call(name.to_string(), vec![arg.clone()]);
+    match name {
+        "sin" => negate(call("cos")),
+        "cos" => Ok(call("sin")),
+        "sqrt" => quotient(
+            product(int(2), product(arg.clone(), call("sqrt"))?)?,
+            int(3),
+        ),
+        "cbrt" => quotient(
+            product(int(3), product(arg.clone(), call("cbrt"))?)?,
+            int(4),
+        ),
+        "square" => quotient(power(arg.clone(), int(3))?, int(3)),
+        "cube" => quotient(power(arg.clone(), int(4))?, int(4)),
+        _ => Err(MathError::NoClosedForm),
+    }
+}
+
+/// Splits off the number an expression is multiplied by, e.g. `-2x` is `-2` and `x`.
+fn coefficient(expr: Expr) -> (Token, Expr) {
+    match expr {
+        Expr::Number(token) => (token, int(1)),
+        Expr::Mul(lhs, rhs) => match *lhs {
+            Expr::Number(token) => (token, *rhs),
+            lhs => (
+                Token::Basic(BasicToken::Integer(1)),
+                Expr::Mul(Box::new(lhs), rhs),
+            ),
+        },
+        expr => (Token::Basic(BasicToken::Integer(1)), expr),
+    }
+}
+
+/// `coefficient*expr` without any further simplification.
+fn scaled(coefficient: Token, expr: Expr) -> Expr {
+    match (Expr::Number(coefficient), expr) {
+        (coefficient, expr) if expr.is_one() => coefficient,
+        (coefficient, expr) if coefficient.is_one() => expr,
+        (coefficient, expr) => Expr::Mul(Box::new(coefficient), Box::new(expr)),
+    }
+}
+
+/// Every factor of a product that has no number in front.
+fn factors(expr: Expr, into: &mut Vec<Expr>) {
+    match expr {
+        Expr::Mul(lhs, rhs) => {
+            factors(*lhs, into);
+            factors(*rhs, into);
+        }
+        expr if expr.is_one() => {}
+        expr => into.push(expr),
+    }
+}
+
+/// Factors are sorted so that equal products are written the same way, symbols first as in
+/// `2x*sin(x)`.
+fn factor_order(expr: &Expr) -> (u8, String) {
+    let rank = match expr {
+        Expr::Symbol(_) => 0,
+        Expr::Pow(base, _) if matches!(**base, Expr::Symbol(_)) => 1,
+        Expr::Call(..) => 2,
+        _ => 3,
+    };
+    (rank, expr.to_string())
+}
+
+/// `lhs + rhs`, written as a subtraction if `rhs` is negative.
+pub fn sum(lhs: Expr, rhs: Expr) -> Result<Expr, MathError> {
+    Ok(match (lhs, rhs) {
+        (Expr::Number(lhs), Expr::Number(rhs)) => Expr::Number(number::add(lhs, rhs)?),
+        (lhs, rhs) if lhs.is_zero() => rhs,
+        (lhs, rhs) if rhs.is_zero() => lhs,
+        (lhs, rhs) if coefficient(rhs.clone()).0.double() < 0.0 => difference(lhs, negate(rhs)?)?,
+        (lhs, rhs) => Expr::Add(Box::new(lhs), Box::new(rhs)),
+    })
+}
+
+/// `lhs - rhs`, written as an addition if `rhs` is negative.
+pub fn difference(lhs: Expr, rhs: Expr) -> Result<Expr, MathError> {
+    Ok(match (lhs, rhs) {
+        (Expr::Number(lhs), Expr::Number(rhs)) => Expr::Number(number::sub(lhs, rhs)?),
+        (lhs, rhs) if rhs.is_zero() => lhs,
+        (lhs, rhs) if lhs.is_zero() => negate(rhs)?,
+        (lhs, rhs) if lhs == rhs => int(0),
+        (lhs, rhs) if coefficient(rhs.clone()).0.double() < 0.0 => sum(lhs, negate(rhs)?)?,
+        (lhs, rhs) => Expr::Sub(Box::new(lhs), Box::new(rhs)),
+    })
+}
+
+fn negate(expr: Expr) -> Result<Expr, MathError> {
+    product(int(-1), expr)
+}
+
+/// `lhs*rhs` with the numbers multiplied together in front, e.g. `x*2*sin(x)*x` is
+/// `2x^2*sin(x)`.
+pub fn product(lhs: Expr, rhs: Expr) -> Result<Expr, MathError> {
+    Ok(match (lhs, rhs) {
+        (Expr::Number(lhs), Expr::Number(rhs)) => Expr::Number(number::mul(lhs, rhs)?),
+        (lhs, rhs) if lhs.is_zero() || rhs.is_zero() => int(0),
+        (Expr::Div(numerator, denominator), other) | (other, Expr::Div(numerator, denominator)) => {
+            quotient(product(other, *numerator)?, *denominator)?
+        }
+        (lhs, rhs) => {
+            let ((l_coefficient, lhs), (r_coefficient, rhs)) = (coefficient(lhs), coefficient(rhs));
+            let mut all = vec![];
+            factors(lhs, &mut all);
+            factors(rhs, &mut all);
+            all.sort_by_cached_key(factor_order);
+            // Equal factors next to each other become a power.
+            let mut grouped: Vec<(Expr, i128)> = vec![];
+            for factor in all {
+                match grouped.last_mut() {
+                    Some((last, count)) if *last == factor => *count += 1,
+                    _ => grouped.push((factor, 1)),
+                }
+            }
+            let mut core: Option<Expr> = None;
+            for (factor, count) in grouped {
+                let factor = power(factor, int(count))?;
+                core = Some(match core {
+                    Some(core) => Expr::Mul(Box::new(core), Box::new(factor)),
+                    None => factor,
+                });
+            }
+            let coefficient = number::mul(l_coefficient, r_coefficient)?;
+            scaled(coefficient, core.unwrap_or_else(|| int(1)))
+        }
+    })
+}
+
+/// `lhs/rhs` with the numbers of both sides cancelled, e.g. `2x/(4y)` is `x/(2y)`.
+pub fn quotient(lhs: Expr, rhs: Expr) -> Result<Expr, MathError> {
+    Ok(match (lhs, rhs) {
+        (Expr::Number(lhs), Expr::Number(rhs)) => Expr::Number(number::div(lhs, rhs)?),
+        (lhs, rhs) if rhs.is_one() => lhs,
+        (lhs, rhs) if lhs.is_zero() && !rhs.is_zero() => int(0),
+        (lhs, rhs) => {
+            let ((l_coefficient, lhs), (r_coefficient, rhs)) = (coefficient(lhs), coefficient(rhs));
+            let coefficient = number::div(l_coefficient, r_coefficient)?;
+            if lhs == rhs {
+                return Ok(Expr::Number(coefficient));
+            }
+            let (numerator, denominator) = match &coefficient {
+                Token::Basic(BasicToken::Integer(i)) => (*i, 1),
+                Token::Basic(BasicToken::Fraction(fr)) => (
+                    fr.int
+                        .checked_mul(fr.den)
+                        .and_then(|int| int.checked_add(fr.num))
+                        .ok_or(MathError::Overflow)?,
+                    fr.den,
+                ),
+                _ => return Ok(scaled(coefficient, Expr::Div(Box::new(lhs), Box::new(rhs)))),
+            };
+            let numerator = scaled(Token::Basic(BasicToken::Integer(numerator)), lhs);
+            let denominator = scaled(Token::Basic(BasicToken::Integer(denominator)), rhs);
+            match denominator.is_one() {
+                true => numerator,
+                false => Expr::Div(Box::new(numerator), Box::new(denominator)),
+            }
+        }
+    })
+}
+
+pub fn power(base: Expr, exponent: Expr) -> Result<Expr, MathError> {
+    Ok(match (base, exponent) {
+        (Expr::Number(base), Expr::Number(exponent)) => Expr::Number(number::exp(base, exponent)?),
+        (base, exponent) if exponent.is_one() => base,
+        (_, exponent) if exponent.is_zero() => int(1),
+        (Expr::Pow(base, inner), Expr::Number(outer)) if matches!(*inner, Expr::Number(_)) => {
+            power(*base, product(*inner, Expr::Number(outer))?)?
+        }
+        (base, exponent) => Expr::Pow(Box::new(base), Box::new(exponent)),
+    })
+}
+
+impl From<&Polynomial> for Expr {
+    fn from(polynomial: &Polynomial) -> Expr {
+        let mut terms = polynomial
+            .sorted_terms()
+            .into_iter()
+            .map(|(monomial, coefficient)| {
+                monomial.iter().fold(
+                    Expr::Number(coefficient.clone()),
+                    |term, (symbol, power)| {
+                        let factor = match power {
+                            1 => Expr::Symbol(symbol.clone()),
+                            _ => Expr::Pow(
+                                Box::new(Expr::Symbol(symbol.clone())),
+                                Box::new(int(*power as i128)),
+                            ),
+                        };
+                        match term.is_one() {
+                            true => factor,
+                            false => Expr::Mul(Box::new(term), Box::new(factor)),
+                        }
+                    },
+                )
+            });
+        let first = terms.next().unwrap_or_else(|| int(0));
+        terms.fold(first, |lhs, rhs| Expr::Add(Box::new(lhs), Box::new(rhs)))
+    }
+}
+
+impl TryFrom<Value> for Expr {
+    type Error = MathError;
+
+    fn try_from(value: Value) -> Result<Expr, MathError> {
+        match value {
+            Value::Number(token) => Ok(Expr::Number(token)),
+            Value::Polynomial(polynomial) => Ok(Expr::from(&polynomial)),
+            Value::Expression(expr) => Ok(expr),
+            Value::Matrix(_) | Value::List(_) | Value::Solutions(_) | Value::LinearSolutions(_) => {
+                Err(MathError::NotANumber)
+            }
+        }
+    }
+}
+
+/// Whether `expr` is written starting with a digit, so that it cannot follow a number.
+fn starts_with_number(expr: &Expr) -> bool {
+    match expr {
+        Expr::Number(_) => true,
+        Expr::Add(lhs, _)
+        | Expr::Sub(lhs, _)
+        | Expr::Mul(lhs, _)
+        | Expr::Div(lhs, _)
+        | Expr::Pow(lhs, _) => lhs.precedence() >= 3 && starts_with_number(lhs),
+        Expr::Symbol(_) | Expr::Call(..) => false,
+    }
+}
+
+/// Writes `expr`, in brackets if it binds less tightly than `precedence`.
+fn fmt_operand(f: &mut fmt::Formatter, expr: &Expr, precedence: u8) -> fmt::Result {
+    match expr.precedence() < precedence {
+        true => write!(f, "({})", expr),
+        false => write!(f, "{}", expr),
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(Token::Basic(BasicToken::Integer(i))) => write!(f, "{}", i),
+            Expr::Number(token) => write!(f, "({})", token),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Add(lhs, rhs) => write!(f, "{} + {}", lhs, rhs),
+            Expr::Sub(lhs, rhs) => {
+                write!(f, "{} - ", lhs)?;
+                fmt_operand(f, rhs, 2)
+            }
+            Expr::Mul(lhs, rhs) if lhs.as_ref() == &int(-1) => {
+                write!(f, "-")?;
+                fmt_operand(f, rhs, 2)
+            }
+            // Numbers are written in front of what they multiply, as in 3x^2 or 2(x + 1).
+            Expr::Mul(lhs, rhs) if matches!(**lhs, Expr::Number(_)) && !starts_with_number(rhs) => {
+                write!(f, "{}", lhs)?;
+                fmt_operand(f, rhs, 2)
+            }
+            Expr::Mul(lhs, rhs) => {
+                fmt_operand(f, lhs, 2)?;
+                write!(f, "*")?;
+                fmt_operand(f, rhs, 2)
+            }
+            Expr::Div(lhs, rhs) => {
+                fmt_operand(f, lhs, 2)?;
+                write!(f, "/")?;
+                fmt_operand(f, rhs, 3)
+            }
+            Expr::Pow(base, exponent) => {
+                fmt_operand(f, base, 4)?;
+                write!(f, "^")?;
+                fmt_operand(f, exponent, 4)
+            }
+            // Roots are written with their sign like exact numbers, as in 1/(2√x) or √(x^2 + 1).
+            Expr::Call(name, args)
+                if matches!(name.as_str(), "sqrt" | "cbrt") && args.len() == 1 =>
+            {
+                write!(f, "{}", if name == "sqrt" { "√" } else { "∛" })?;
+                match &args[0] {
+                    arg @ (Expr::Symbol(_)
+                    | Expr::Number(Token::Basic(BasicToken::Integer(0..)))) => {
+                        write!(f, "{}", arg)
+                    }
+                    arg => write!(f, "({})", arg),
+                }
+            }
+            Expr::Call(name, args) => {
+                let args: Vec<String> = args.iter().map(Expr::to_string).collect();
+                write!(f, "{}({})", name, args.join("; "))
+            }
+        }
+    }
+}
+
+/// Builds expressions from the parsed input.
+struct Builder<'a> {
+    env: &'a Environment,
+    symbol: &'a str,
+    /// The arguments of the user function being inlined.
+    bindings: BTreeMap<String, Expr>,
+    depth: usize,
+}
+
+impl Builder<'_> {
+    fn build(&self, expression: Pairs<Rule>) -> Result<Expr, EvalError> {
+        PREC_CLIMBER.climb(
+            expression,
+            |pair| self.term(pair),
+            |lhs: Result<Expr, EvalError>, op: Pair<Rule>, rhs: Result<Expr, EvalError>| {
+                let (lhs, rhs) = (lhs?, rhs?);
+                match op.as_rule() {
+                    Rule::add => sum(lhs, rhs),
+                    Rule::subtract => difference(lhs, rhs),
+                    Rule::multiply => product(lhs, rhs),
+                    Rule::divide => quotient(lhs, rhs),
+                    Rule::power => power(lhs, rhs),
+                    _ => unreachable!(),
+                }
+                .map_err(|kind| error_at(kind, &op))
+            },
+        )
+    }
+
+    fn term(&self, pair: Pair<Rule>) -> Result<Expr, EvalError> {
+        match pair.as_rule() {
+            Rule::expr => self.build(pair.into_inner()),
+            Rule::ident => {
+                let name = pair.as_str();
+                if let Some(expr) = self.bindings.get(name) {
+                    return Ok(expr.clone());
+                }
+                match self.env.get(name) {
+                    _ if name == self.symbol => Ok(Expr::Symbol(name.to_string())),
+                    Some(value) => {
+                        Expr::try_from(value.clone()).map_err(|kind| error_at(kind, &pair))
+                    }
+                    None => Ok(match self.env.registry().constant(name) {
+                        Some(constant) => Expr::Number(constant.clone()),
+                        None => Expr::Symbol(name.to_string()),
+                    }),
+                }
+            }
+            Rule::answer => {
+                let value = answer_eval(&pair, self.env)?.clone();
+                Expr::try_from(value).map_err(|kind| error_at(kind, &pair))
+            }
+            Rule::call => self.call(pair),
+            Rule::vector | Rule::list => Err(error_at(MathError::NotANumber, &pair)),
+            Rule::root => {
+                let mut root = pair.into_inner();
+                let name = match root.next().unwrap().as_rule() {
+                    Rule::sqrt_sign => "sqrt",
+                    _ => "cbrt",
+                };
+                let arg = self.term(root.next().unwrap())?;
+                Ok(Expr::Call(name.to_string(), vec![arg]))
+            }
+            _ => {
+                let error_pair = pair.clone();
+                number_eval(pair)
+                    .map(Expr::Number)
+                    .map_err(|kind| error_at(kind, &error_pair))
+            }
+        }
+    }
+
+    fn call(&self, call: Pair<Rule>) -> Result<Expr, EvalError> {
+        let name = call.clone().into_inner().next().unwrap().as_str();
+        if is_special_call(name, &call) {
+            // Such as a nested diff, these are evaluated as usual.
+            let value = call_eval(call.clone(), self.env)?;
+            return Expr::try_from(value).map_err(|kind| error_at(kind, &call));
+        }
+        let args = call
+            .clone()
+            .into_inner()
+            .skip(1)
+            .map(|arg| match arg.as_rule() {
+                Rule::equation => Err(error_at(MathError::UnexpectedEquation, &arg)),
+                Rule::comparison => Err(error_at(MathError::UnexpectedComparison, &arg)),
+                _ => self
+                    .build(arg.into_inner())
+                    .map_err(|error| error_at(error.kind, &call).caused_by(error)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match resolve(&call, self.env, args.len())? {
+            Callee::User(function) => {
+                if self.depth >= MAX_CALL_DEPTH {
+                    return Err(error_at(MathError::RecursionLimit, &call));
+                }
+                let inlined = Builder {
+                    env: self.env,
+                    symbol: self.symbol,
+                    bindings: function.params.iter().cloned().zip(args).collect(),
+                    depth: self.depth + 1,
+                };
+                inlined
+                    .build(Expression::parse(Rule::calculation, &function.body)?)
+                    .map_err(|error| body_error(error, &call))
+            }
+            Callee::Builtin(_) => Ok(Expr::Call(name.to_string(), args)),
+            Callee::Variable(value) => {
+                let value = Expr::try_from(value.clone()).map_err(|kind| error_at(kind, &call))?;
+                let arg = args.into_iter().next().unwrap();
+                product(value, arg).map_err(|kind| error_at(kind, &call))
+            }
+        }
+    }
+}
+
+/// Builds the expression of `arg` where `symbol` has no value.
+pub(crate) fn symbolic_eval(
+    arg: Pair<Rule>,
+    symbol: &str,
+    env: &Environment,
+) -> Result<Expr, EvalError> {
+    match arg.as_rule() {
+        Rule::equation => return Err(error_at(MathError::UnexpectedEquation, &arg)),
+        Rule::comparison => return Err(error_at(MathError::UnexpectedComparison, &arg)),
+        _ => {}
+    }
+    let builder = Builder {
+        env,
+        symbol,
+        bindings: BTreeMap::new(),
+        depth: 0,
+    };
+    builder.build(arg.into_inner())
+}
@@ -1,6 +1,12 @@
+use crate::precision::{inexact, LossReason, Operation};
+use crate::steps;
 use crate::types::{BasicToken, Fraction, MathError, SRoot, Token};
 
 pub fn sin(number: Token) -> Result<Token, MathError> {
+    steps::trig_value("sin", number, sin_value)
+}
+
+fn sin_value(number: Token) -> Result<Token, MathError> {
     match number {
         Token::Basic(BasicToken::Integer(0)) | Token::Pi(BasicToken::Integer(_)) => {
             Ok(Token::Basic(BasicToken::Integer(0)))
@@ -79,21 +85,36 @@ pub fn sin(number: Token) -> Result<Token, MathError> {
                     vec![],
                 ),
                 (1, 2, true) => Token::Basic(BasicToken::Integer(-1)),
-                (num, den, false) => Token::Basic(BasicToken::Double(
-                    ((num as f64 / den as f64) * std::f64::consts::PI).sin(),
-                )),
-                (num, den, true) => Token::Basic(BasicToken::Double(
-                    (-(num as f64 / den as f64) * std::f64::consts::PI).sin(),
-                )),
+                (num, den, false) => {
+                    return inexact(
+                        Operation::Sin,
+                        vec![number],
+                        LossReason::NonTableAngle,
+                        ((num as f64 / den as f64) * std::f64::consts::PI).sin(),
+                    )
+                }
+                (num, den, true) => {
+                    return inexact(
+                        Operation::Sin,
+                        vec![number],
+                        LossReason::NonTableAngle,
+                        (-(num as f64 / den as f64) * std::f64::consts::PI).sin(),
+                    )
+                }
             })
         }
-        val => Ok(Token::Basic(BasicToken::Double(
-            trig_check!(val.double()).sin(),
-        ))),
+        val => {
+            let value = trig_check!(val.double()).sin();
+            inexact(Operation::Sin, vec![val], LossReason::NonTableAngle, value)
+        }
     }
 }
 
 pub fn asin(number: Token) -> Result<Token, MathError> {
+    steps::trig_value("asin", number, asin_value)
+}
+
+fn asin_value(number: Token) -> Result<Token, MathError> {
     use self::Fraction as FractionStruct;
     use BasicToken::*;
     use Token::*;
@@ -236,7 +257,12 @@ pub fn asin(number: Token) -> Result<Token, MathError> {
                 let double = val.double();
                 match !(-1.0..=1.0).contains(&double) {
                     true => Err(MathError::DomainError),
-                    false => Ok(Basic(Double(double.asin()))),
+                    false => inexact(
+                        Operation::Asin,
+                        vec![Combined(val)],
+                        LossReason::NonTableValue,
+                        double.asin(),
+                    ),
                 }
             }
         },
@@ -293,13 +319,22 @@ pub fn asin(number: Token) -> Result<Token, MathError> {
             let double = val.double();
             match !(-1.0..=1.0).contains(&double) {
                 true => Err(MathError::DomainError),
-                false => Ok(Basic(Double(double.asin()))),
+                false => inexact(
+                    Operation::Asin,
+                    vec![val],
+                    LossReason::NonTableValue,
+                    double.asin(),
+                ),
             }
         }
     }
 }
 
 pub fn cos(number: Token) -> Result<Token, MathError> {
+    steps::trig_value("cos", number, cos_value)
+}
+
+fn cos_value(number: Token) -> Result<Token, MathError> {
     match number {
         Token::Basic(BasicToken::Integer(0)) => Ok(Token::Basic(BasicToken::Integer(1))),
         Token::Pi(BasicToken::Integer(x)) => match x % 2 == 1 {
@@ -380,22 +415,37 @@ pub fn cos(number: Token) -> Result<Token, MathError> {
                         vec![],
                     ),
                     (1, 2, _) => Token::Basic(BasicToken::Integer(0)),
-                    (num, den, true) => Token::Basic(BasicToken::Double(
-                        -((num as f64 / den as f64) * std::f64::consts::PI).cos(),
-                    )),
-                    (num, den, false) => Token::Basic(BasicToken::Double(
-                        ((num as f64 / den as f64) * std::f64::consts::PI).cos(),
-                    )),
+                    (num, den, true) => {
+                        return inexact(
+                            Operation::Cos,
+                            vec![number],
+                            LossReason::NonTableAngle,
+                            -((num as f64 / den as f64) * std::f64::consts::PI).cos(),
+                        )
+                    }
+                    (num, den, false) => {
+                        return inexact(
+                            Operation::Cos,
+                            vec![number],
+                            LossReason::NonTableAngle,
+                            ((num as f64 / den as f64) * std::f64::consts::PI).cos(),
+                        )
+                    }
                 },
             )
         }
-        val => Ok(Token::Basic(BasicToken::Double(
-            trig_check!(val.double()).cos(),
-        ))),
+        val => {
+            let value = trig_check!(val.double()).cos();
+            inexact(Operation::Cos, vec![val], LossReason::NonTableAngle, value)
+        }
     }
 }
 
 pub fn acos(number: Token) -> Result<Token, MathError> {
+    steps::trig_value("acos", number, acos_value)
+}
+
+fn acos_value(number: Token) -> Result<Token, MathError> {
     use self::Fraction as FractionStruct;
     use BasicToken::*;
     use Token::*;
@@ -538,7 +588,12 @@ pub fn acos(number: Token) -> Result<Token, MathError> {
                 let double = val.double();
                 match !(-1.0..=1.0).contains(&double) {
                     true => Err(MathError::DomainError),
-                    false => Ok(Basic(Double(double.acos()))),
+                    false => inexact(
+                        Operation::Acos,
+                        vec![Combined(val)],
+                        LossReason::NonTableValue,
+                        double.acos(),
+                    ),
                 }
             }
         },
@@ -599,13 +654,22 @@ pub fn acos(number: Token) -> Result<Token, MathError> {
             let double = val.double();
             match !(-1.0..=1.0).contains(&double) {
                 true => Err(MathError::DomainError),
-                false => Ok(Basic(Double(double.acos()))),
+                false => inexact(
+                    Operation::Acos,
+                    vec![val],
+                    LossReason::NonTableValue,
+                    double.acos(),
+                ),
             }
         }
     }
 }
 
 pub fn tan(number: Token) -> Result<Token, MathError> {
+    steps::trig_value("tan", number, tan_value)
+}
+
+fn tan_value(number: Token) -> Result<Token, MathError> {
     match number {
         Token::Basic(BasicToken::Integer(0)) | Token::Pi(BasicToken::Integer(_)) => {
             Ok(Token::Basic(BasicToken::Integer(0)))
@@ -730,25 +794,36 @@ pub fn tan(number: Token) -> Result<Token, MathError> {
                     vec![],
                 ),
                 (1, 2, _) => return Err(MathError::TangentError),
-                (num, den, true) => Token::Basic(BasicToken::Double(double_check!(-((num
-                    as f64
-                    / den as f64)
-                    * std::f64::consts::PI)
-                    .tan()))),
-                (num, den, false) => Token::Basic(BasicToken::Double(double_check!(((num
-                    as f64
-                    / den as f64)
-                    * std::f64::consts::PI)
-                    .tan()))),
+                (num, den, true) => {
+                    return inexact(
+                        Operation::Tan,
+                        vec![number],
+                        LossReason::NonTableAngle,
+                        -((num as f64 / den as f64) * std::f64::consts::PI).tan(),
+                    )
+                }
+                (num, den, false) => {
+                    return inexact(
+                        Operation::Tan,
+                        vec![number],
+                        LossReason::NonTableAngle,
+                        ((num as f64 / den as f64) * std::f64::consts::PI).tan(),
+                    )
+                }
             })
         }
-        val => Ok(Token::Basic(BasicToken::Double(
-            trig_check!(val.double()).tan(),
-        ))),
+        val => {
+            let value = trig_check!(val.double()).tan();
+            inexact(Operation::Tan, vec![val], LossReason::NonTableAngle, value)
+        }
     }
 }
 
 pub fn atan(number: Token) -> Result<Token, MathError> {
+    steps::trig_value("atan", number, atan_value)
+}
+
+fn atan_value(number: Token) -> Result<Token, MathError> {
     use self::Fraction as FractionStruct;
     use BasicToken::*;
     use Token::*;
@@ -794,7 +869,12 @@ pub fn atan(number: Token) -> Result<Token, MathError> {
                 let double = val.double();
                 match !(-1.0..=1.0).contains(&double) {
                     true => Err(MathError::DomainError),
-                    false => Ok(Basic(Double(double.atan()))),
+                    false => inexact(
+                        Operation::Atan,
+                        vec![Combined(val)],
+                        LossReason::NonTableValue,
+                        double.atan(),
+                    ),
                 }
             }
         },
@@ -810,6 +890,9 @@ pub fn atan(number: Token) -> Result<Token, MathError> {
             base: 3,
         })) => Ok(Pi(BasicToken::fraction(0, 1, 6))),
         Basic(SIntRoot(SRoot { mul: 1, base: 3 })) => Ok(Pi(BasicToken::fraction(0, 1, 3))),
-        val => Ok(Basic(Double(val.double().atan()))),
+        val => {
+            let value = val.double().atan();
+            inexact(Operation::Atan, vec![val], LossReason::NonTableValue, value)
+        }
     }
 }
@@ -1,7 +1,7 @@
 use crate::number::{try_add, try_mul};
+use crate::precision::{inexact, record, LossReason, Operation};
 use core::fmt;
 use gcd::Gcd;
-use std::mem::discriminant;
 use std::ops::{Add, Div, Mul, Sub};
 
 #[derive(PartialEq, Copy, Clone)]
@@ -53,7 +53,7 @@ impl BasicToken {
 
     /// This function does not check if the f64 is valid as such it is recommended to check with double_check!() once the computations are finished.
     pub fn double(&self) -> f64 {
-        match &*self {
+        match self {
             BasicToken::Integer(i) => *i as f64,
             BasicToken::Fraction(i) => i.int as f64 + i.num as f64 / i.den as f64,
             BasicToken::SIntRoot(i) => (i.mul as f64) * (i.base as f64).sqrt(),
@@ -67,6 +67,9 @@ impl BasicToken {
             BasicToken::Double(i) => *i,
         }
     }
+    pub fn is_exact(&self) -> bool {
+        !matches!(self, BasicToken::Double(_))
+    }
     pub fn negate(self) -> Result<BasicToken, MathError> {
         Ok(match self {
             BasicToken::Integer(i) => BasicToken::Integer(mul!(i, -1)),
@@ -140,6 +143,28 @@ impl Token {
             Token::Combined(i) => i.double(),
         }
     }
+    pub fn is_exact(&self) -> bool {
+        match self {
+            Token::Basic(x) | Token::Pi(x) => x.is_exact(),
+            Token::Combined(i) => i
+                .basic
+                .vec
+                .iter()
+                .chain(i.pi.vec.iter())
+                .all(BasicToken::is_exact),
+        }
+    }
+    /// Whether the value is zero, without rounding exact values that are merely close to it.
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Token::Basic(x) | Token::Pi(x) => match x {
+                BasicToken::Integer(i) => *i == 0,
+                BasicToken::Double(d) => *d == 0.0,
+                _ => false,
+            },
+            Token::Combined(_) => false,
+        }
+    }
     pub fn negate(self) -> Result<Token, MathError> {
         Ok(match self {
             Token::Basic(x) => Token::Basic(x.negate()?),
@@ -179,6 +204,210 @@ impl fmt::Debug for Token {
     }
 }
 
+/// Doubles at least this large or smaller than [`SMALL_DOUBLE`] are written with a power of ten.
+const LARGE_DOUBLE: f64 = 1e16;
+const SMALL_DOUBLE: f64 = 1e-6;
+
+/// The digits of a double and the power of ten they are multiplied by, e.g. `("1.5", Some(20))`
+/// for 1.5e20. Doubles of a readable size are written out in full and have no power.
+pub(crate) fn double_parts(value: f64) -> (String, Option<i32>) {
+    let size = value.abs();
+    if size == 0.0 || !size.is_finite() || (SMALL_DOUBLE..LARGE_DOUBLE).contains(&size) {
+        return (value.to_string(), None);
+    }
+    let scientific = format!("{:e}", value);
+    match scientific.split_once('e') {
+        Some((digits, power)) => (digits.to_string(), power.parse().ok()),
+        None => (scientific, None),
+    }
+}
+
+/// Writes a double, such as `2^200` as `1.6069380442589903e60` rather than with 61 digits.
+pub(crate) fn fmt_double(value: f64) -> String {
+    match double_parts(value) {
+        (digits, Some(power)) => format!("{}e{}", digits, power),
+        (digits, None) => digits,
+    }
+}
+
+/// Writes numbers the way they are written by hand, such as `√6/4 - √2/4`, `π/2` and `11/2`.
+///
+/// Fractions are improper by default, the alternate flag `{:#}` writes them as mixed numbers
+/// like `5½` instead.
+impl fmt::Display for BasicToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", fmt_term(self, false, f.alternate()))
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (pos, (tok, pi)) in self.terms().into_iter().enumerate() {
+            let term = fmt_term(tok, pi, f.alternate());
+            match (pos, term.strip_prefix('-')) {
+                (0, _) => write!(f, "{}", term)?,
+                (_, Some(negated)) => write!(f, " - {}", negated)?,
+                (_, None) => write!(f, " + {}", term)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Token {
+    /// The terms of the number in the order they are written, each with whether it is a
+    /// multiple of π.
+    ///
+    /// Positive terms come first, each sign ordered by rational, square root, cube root, decimal
+    /// and then the multiples of π.
+    pub(crate) fn terms(&self) -> Vec<(&BasicToken, bool)> {
+        let mut terms: Vec<_> = match self {
+            Token::Basic(tok) => vec![(tok, false)],
+            Token::Pi(tok) => vec![(tok, true)],
+            Token::Combined(v) => v
+                .basic
+                .vec
+                .iter()
+                .map(|tok| (tok, false))
+                .chain(v.pi.vec.iter().map(|tok| (tok, true)))
+                .collect(),
+        };
+        terms.sort_by_key(|(tok, pi)| {
+            let (kind, base) = match tok {
+                BasicToken::Integer(_) | BasicToken::Fraction(_) => (0, 0),
+                BasicToken::SIntRoot(r) => (1, r.base),
+                BasicToken::SFracRoot(r) => (1, r.base),
+                BasicToken::CIntRoot(r) => (2, r.base),
+                BasicToken::CFracRoot(r) => (2, r.base),
+                BasicToken::Double(_) => (3, 0),
+            };
+            (tok.double() < 0.0, *pi, kind, base)
+        });
+        terms
+    }
+}
+
+/// An exact term of a number split into the parts it is written with, `-3π√2/4` is negative
+/// with the numerator 3, π, the square root of 2 and the denominator 4.
+pub(crate) struct Term {
+    pub negative: bool,
+    /// The whole part of the multiplier without its sign.
+    pub int: u128,
+    /// The proper fraction of the multiplier without its sign, 0/1 for integers.
+    pub num: u128,
+    pub den: u128,
+    pub pi: bool,
+    /// The index and base of the root, such as (3, 2) for ∛2.
+    pub root: Option<(u8, i128)>,
+}
+
+impl Term {
+    /// Splits `tok`, times π if `pi` is set, or returns `None` for a double.
+    pub(crate) fn new(tok: &BasicToken, pi: bool) -> Option<Term> {
+        let (mul, root) = match tok {
+            BasicToken::Double(_) => return None,
+            BasicToken::Integer(i) => (Fraction::new(*i, 0, 1), None),
+            BasicToken::Fraction(fr) => (*fr, None),
+            BasicToken::SIntRoot(r) => (Fraction::new(r.mul, 0, 1), Some((2, r.base))),
+            BasicToken::SFracRoot(r) => (r.mul, Some((2, r.base))),
+            BasicToken::CIntRoot(r) => (Fraction::new(r.mul, 0, 1), Some((3, r.base))),
+            BasicToken::CFracRoot(r) => (r.mul, Some((3, r.base))),
+        };
+        let (negative, int, num) = mul.mixed_parts();
+        Some(Term {
+            negative,
+            int,
+            num,
+            den: mul.den.unsigned_abs(),
+            pi,
+            root,
+        })
+    }
+
+    /// The numerator of the multiplier as an improper fraction, `None` if it does not fit.
+    pub(crate) fn numerator(&self) -> Option<u128> {
+        self.int.checked_mul(self.den)?.checked_add(self.num)
+    }
+
+    /// Whether the term is only a number, without π or a root.
+    pub(crate) fn is_rational(&self) -> bool {
+        !self.pi && self.root.is_none()
+    }
+}
+
+/// Writes `tok`, times π if `pi` is set, with a leading `-` if it is negative.
+fn fmt_term(tok: &BasicToken, pi: bool, mixed: bool) -> String {
+    let term = match Term::new(tok, pi) {
+        Some(term) => term,
+        None => return format!("{}{}", fmt_double(tok.double()), if pi { "π" } else { "" }),
+    };
+    let sign = if term.negative { "-" } else { "" };
+    let mut unit = String::new();
+    if term.pi {
+        unit.push('π');
+    }
+    match term.root {
+        Some((2, base)) => unit.push_str(&format!("√{}", base)),
+        Some((_, base)) => unit.push_str(&format!("∛{}", base)),
+        None => {}
+    }
+    let den = match term.den {
+        1 => String::new(),
+        den => format!("/{}", den),
+    };
+    match (term.numerator(), term.is_rational()) {
+        (Some(num), true) if !mixed || term.int == 0 || term.num == 0 => {
+            format!("{}{}{}", sign, num, den)
+        }
+        // The numerator does not fit or a mixed number was asked for.
+        (_, true) => format!("{}{}", sign, fmt_mixed(&term)),
+        (Some(1), false) => format!("{}{}{}", sign, unit, den),
+        (Some(num), false) => format!("{}{}{}{}", sign, num, unit, den),
+        (None, false) => format!("{}({}){}", sign, fmt_mixed(&term), unit),
+    }
+}
+
+/// Writes the multiplier of a term as a whole number followed by its proper part, such as `5½`
+/// or `2³⁄₁₁`.
+fn fmt_mixed(term: &Term) -> String {
+    let vulgar = match (term.num, term.den) {
+        (1, 2) => "½",
+        (1, 3) => "⅓",
+        (2, 3) => "⅔",
+        (1, 4) => "¼",
+        (3, 4) => "¾",
+        (1, 5) => "⅕",
+        (2, 5) => "⅖",
+        (3, 5) => "⅗",
+        (4, 5) => "⅘",
+        (1, 6) => "⅙",
+        (5, 6) => "⅚",
+        (1, 7) => "⅐",
+        (1, 8) => "⅛",
+        (3, 8) => "⅜",
+        (5, 8) => "⅝",
+        (7, 8) => "⅞",
+        (1, 9) => "⅑",
+        (1, 10) => "⅒",
+        _ => "",
+    };
+    let part = match vulgar {
+        "" => {
+            let digits = |n: u128, digits: [char; 10]| -> String {
+                n.to_string()
+                    .chars()
+                    .map(|c| digits[c as usize - '0' as usize])
+                    .collect()
+            };
+            let sup = digits(term.num, ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹']);
+            let sub = digits(term.den, ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉']);
+            format!("{}⁄{}", sup, sub)
+        }
+        vulgar => vulgar.to_string(),
+    };
+    format!("{}{}", term.int, part)
+}
+
 #[derive(Debug, Eq, Copy, PartialEq, Clone)]
 pub enum MathError {
     None,
@@ -193,6 +422,76 @@ pub enum MathError {
     TrigAccuracy,
     TangentError,
     DomainError,
+    /// Only exact results were allowed but the value had to be rounded.
+    Inexact(LossReason),
+    UndefinedVariable,
+    /// `ans` or `$n` was used before there was such a result.
+    UndefinedAnswer,
+    UndefinedFunction,
+    /// A user function was called with the wrong number of arguments.
+    ArgumentCount {
+        expected: usize,
+        found: usize,
+    },
+    /// User functions called each other too deeply, usually because of endless recursion.
+    RecursionLimit,
+    /// Registered constants and functions cannot be assigned to.
+    ReservedName,
+    /// A polynomial or solutions were used where only a number makes sense, e.g. `sin(x)`.
+    NotANumber,
+    /// The result would be a rational function or have symbols in an exponent.
+    NotPolynomial,
+    /// An argument had to be a symbol, like the `x` in `collect(p; x)`.
+    ExpectedSymbol,
+    /// An equation was used somewhere other than an argument of `solve`.
+    UnexpectedEquation,
+    /// A comparison such as `x < 1` was used somewhere other than a condition of `if`.
+    UnexpectedComparison,
+    /// The equation is not a polynomial or its roots cannot be written exactly.
+    NoClosedForm,
+    /// The calculation only makes sense with exact values, e.g. `solve` in float mode.
+    ExactOnly,
+    /// The derivative cannot be written with the supported functions, e.g. `diff(2^x; x)`.
+    NoDerivative,
+    /// A numerical method did not find a result, e.g. `nsolve` of an equation without roots.
+    NoConvergence,
+    /// An integral is infinite, e.g. the integral of 1/x^2 from -1 to 1.
+    Divergent,
+    /// Numerical integration did not reach the tolerance, e.g. for a quickly oscillating integrand.
+    NotIntegrable,
+    /// An argument had to be a whole number, like the bounds of `sum`.
+    ExpectedInteger,
+    /// A sum or product has too many terms to evaluate one by one and no known closed form.
+    TooManyTerms,
+    /// The sizes of two matrices do not fit the operation, e.g. adding a 2×2 and a 3×3 matrix.
+    SizeMismatch {
+        lhs: (u32, u32),
+        rhs: (u32, u32),
+    },
+    /// The rows of a matrix literal have different lengths.
+    RaggedMatrix,
+    /// Only square matrices have a determinant or an inverse.
+    NotSquare {
+        rows: u32,
+        cols: u32,
+    },
+    /// The matrix has no inverse because its determinant is zero.
+    SingularMatrix,
+    /// An argument had to be a matrix, like the argument of `det`.
+    ExpectedMatrix,
+    /// An equation of `linsolve` has a product or power of unknowns, as in `x*y = 1`.
+    NotLinear,
+    /// Two lists used in an element-wise operation have different lengths.
+    LengthMismatch {
+        lhs: u32,
+        rhs: u32,
+    },
+    /// The list has no elements but at least one is needed, e.g. for `reduce` without a start.
+    EmptyList,
+    /// An argument had to be a list, like the second argument of `map`.
+    ExpectedList,
+    /// A `range` would have more than [`crate::list::MAX_LENGTH`] elements.
+    ListTooLong,
 }
 
 impl fmt::Display for MathError {
@@ -211,10 +510,183 @@ impl fmt::Display for MathError {
             MathError::DomainError => {
                 write!(f, "Function cannot compute values outside of it's domain.")
             }
+            MathError::Inexact(reason) => write!(f, "Cannot compute exactly, {}", reason),
+            MathError::UndefinedVariable => write!(f, "Undefined variable"),
+            MathError::UndefinedAnswer => write!(f, "No previous result with that number"),
+            MathError::UndefinedFunction => write!(f, "Undefined function"),
+            MathError::ArgumentCount { expected, found } => {
+                write!(f, "Expected {} arguments but got {}", expected, found)
+            }
+            MathError::RecursionLimit => write!(f, "Too many nested function calls"),
+            MathError::ReservedName => write!(f, "Cannot assign to a built-in name"),
+            MathError::NotANumber => write!(f, "Expected a number"),
+            MathError::NotPolynomial => write!(f, "Result is not a polynomial"),
+            MathError::ExpectedSymbol => write!(f, "Expected a variable name"),
+            MathError::UnexpectedEquation => write!(f, "Equations can only be solved"),
+            MathError::UnexpectedComparison => {
+                write!(f, "Comparisons can only be used as conditions")
+            }
+            MathError::NoClosedForm => write!(f, "Cannot find an exact solution"),
+            MathError::ExactOnly => write!(f, "Only available with exact numbers"),
+            MathError::NoDerivative => write!(f, "Cannot differentiate this expression"),
+            MathError::NoConvergence => write!(f, "Could not find a solution numerically"),
+            MathError::Divergent => write!(f, "The integral diverges"),
+            MathError::NotIntegrable => write!(f, "Could not integrate numerically"),
+            MathError::ExpectedInteger => write!(f, "Expected a whole number"),
+            MathError::TooManyTerms => write!(f, "Too many terms and no closed form is known"),
+            MathError::SizeMismatch { lhs, rhs } => write!(
+                f,
+                "Matrices of size {}×{} and {}×{} do not fit together",
+                lhs.0, lhs.1, rhs.0, rhs.1
+            ),
+            MathError::RaggedMatrix => write!(f, "Every row of a matrix needs the same length"),
+            MathError::NotSquare { rows, cols } => {
+                write!(
+                    f,
+                    "Expected a square matrix but got a {}×{} one",
+                    rows, cols
+                )
+            }
+            MathError::SingularMatrix => write!(f, "The matrix is not invertible"),
+            MathError::ExpectedMatrix => write!(f, "Expected a matrix"),
+            MathError::NotLinear => write!(f, "The equations are not linear"),
+            MathError::LengthMismatch { lhs, rhs } => {
+                write!(f, "Lists of length {} and {} do not fit together", lhs, rhs)
+            }
+            MathError::EmptyList => write!(f, "The list is empty"),
+            MathError::ExpectedList => write!(f, "Expected a list"),
+            MathError::ListTooLong => write!(f, "The list would be too long"),
         }
     }
 }
 
+/// Byte offsets of a part of the input.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// A [`MathError`] together with where in the input it happened.
+///
+/// Errors raised inside a function call are wrapped by an error pointing at the call, so the
+/// innermost `cause` is the operator or function that actually failed.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EvalError {
+    pub kind: MathError,
+    pub span: Option<Span>,
+    /// The text of the offending operator or function call.
+    pub text: String,
+    pub operands: Vec<Token>,
+    pub cause: Option<Box<EvalError>>,
+}
+
+impl EvalError {
+    pub fn new(kind: MathError) -> EvalError {
+        EvalError {
+            kind,
+            span: None,
+            text: String::new(),
+            operands: vec![],
+            cause: None,
+        }
+    }
+
+    pub fn at(mut self, span: Span, text: &str) -> EvalError {
+        self.span = Some(span);
+        self.text = text.to_string();
+        self
+    }
+
+    pub fn with_operands(mut self, operands: Vec<Token>) -> EvalError {
+        self.operands = operands;
+        self
+    }
+
+    pub fn caused_by(mut self, cause: EvalError) -> EvalError {
+        self.cause = Some(Box::new(cause));
+        self
+    }
+
+    /// The error at the bottom of the cause chain.
+    pub fn root_cause(&self) -> &EvalError {
+        match &self.cause {
+            Some(cause) => cause.root_cause(),
+            None => self,
+        }
+    }
+
+    /// Removes the positions from this error and its causes, used when they are not in the input.
+    pub(crate) fn without_spans(mut self) -> EvalError {
+        self.span = None;
+        self.cause = self.cause.map(|cause| Box::new(cause.without_spans()));
+        self
+    }
+
+    /// Returns the input with a line of carets underneath the innermost part that caused the error.
+    pub fn highlight(&self, input: &str) -> Option<String> {
+        let mut span = self.span;
+        let mut cause = &self.cause;
+        while let Some(error) = cause {
+            span = error.span.or(span);
+            cause = &error.cause;
+        }
+        let span = span?;
+        let start = input.get(..span.start)?.chars().count();
+        let width = input.get(span.start..span.end)?.chars().count().max(1);
+        Some(format!(
+            "{}\n{}{}",
+            input,
+            " ".repeat(start),
+            "^".repeat(width)
+        ))
+    }
+
+    fn fmt_location(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(span) = self.span {
+            match self.text.is_empty() {
+                true => write!(f, " at position {}", span.start)?,
+                false => write!(f, " at `{}` (position {})", self.text, span.start)?,
+            }
+        } else if !self.text.is_empty() {
+            write!(f, " at `{}`", self.text)?;
+        }
+        for (pos, operand) in self.operands.iter().enumerate() {
+            match pos {
+                0 => write!(f, " with operands {}", operand)?,
+                _ => write!(f, " and {}", operand)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<MathError> for EvalError {
+    fn from(kind: MathError) -> EvalError {
+        EvalError::new(kind)
+    }
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_location(f)?;
+        let mut cause = &self.cause;
+        while let Some(error) = cause {
+            write!(f, "\n  caused by: ")?;
+            error.fmt_location(f)?;
+            cause = &error.cause;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Copy, Clone)]
 pub struct Fraction {
     pub int: i128,
@@ -272,6 +744,31 @@ impl Fraction {
         Ok(BasicToken::Fraction(self))
     }
 
+    /// The sign, whole part and proper numerator of the fraction, also when `int` and `num` have
+    /// different signs as in 1 - 1/2 or `num` is not smaller than `den`.
+    pub(crate) fn mixed_parts(&self) -> (bool, u128, u128) {
+        let (mut int, mut num) = match self
+            .int
+            .checked_mul(self.den)
+            .and_then(|int| int.checked_add(self.num))
+        {
+            Some(num) => (num / self.den, num % self.den),
+            // Too large for a single numerator, carry between the parts instead.
+            None => (
+                self.int.saturating_add(self.num / self.den),
+                self.num % self.den,
+            ),
+        };
+        if int > 0 && num < 0 {
+            int -= 1;
+            num += self.den;
+        } else if int < 0 && num > 0 {
+            int += 1;
+            num -= self.den;
+        }
+        (int < 0 || num < 0, int.unsigned_abs(), num.unsigned_abs())
+    }
+
     pub fn negate(mut self) -> Result<Fraction, MathError> {
         self.num = mul!(self.num, -1);
         self.int = mul!(self.int, -1);
@@ -486,20 +983,26 @@ impl Combined {
     }
 
     pub fn mul_combined(mut self, tok: Token) -> Result<Token, MathError> {
+        // Some terms may already be multiplied when an overflow is found.
+        let original = self.clone();
         match tok {
             Token::Basic(BasicToken::Integer(0)) | Token::Pi(BasicToken::Integer(0)) => {
                 Ok(Token::Basic(BasicToken::Integer(0)))
             }
-            Token::Basic(tok) => {
+            Token::Basic(basic) => {
                 for comb_tok in self.basic.vec.iter_mut().chain(self.pi.vec.iter_mut()) {
-                    match try_mul(*comb_tok, tok) {
+                    match try_mul(*comb_tok, basic) {
                         Ok(val) => {
                             *comb_tok = val;
                         }
                         Err(MathError::Overflow) => {
-                            return Ok(Token::Basic(BasicToken::Double(double_check!(
-                                self.double() * tok.double()
-                            ))));
+                            let value = original.double() * tok.double();
+                            return inexact(
+                                Operation::Multiply,
+                                vec![Token::Combined(original), tok],
+                                LossReason::Overflow,
+                                value,
+                            );
                         }
                         Err(val) => {
                             return Err(val);
@@ -509,27 +1012,38 @@ impl Combined {
                 Ok(self.normalise())
             }
             // Transform all the basics into Pi
-            Token::Pi(tok) if self.pi.vec.is_empty() => {
+            Token::Pi(pi) if self.pi.vec.is_empty() => {
                 for comb_tok in self.basic.vec.iter_mut() {
-                    match try_mul(*comb_tok, tok) {
+                    match try_mul(*comb_tok, pi) {
                         Ok(val) => {
                             *comb_tok = val;
                         }
                         Err(MathError::Overflow) => {
-                            return Ok(Token::Basic(BasicToken::Double(double_check!(
-                                self.double() * tok.double()
-                            ))));
+                            let value = original.double() * tok.double();
+                            return inexact(
+                                Operation::Multiply,
+                                vec![Token::Combined(original), tok],
+                                LossReason::Overflow,
+                                value,
+                            );
                         }
                         Err(val) => {
                             return Err(val);
                         }
                     }
                 }
+                std::mem::swap(&mut self.basic, &mut self.pi);
                 Ok(self.normalise())
             }
-            tok => Ok(Token::Basic(BasicToken::Double(double_check!(
-                self.double() * tok.double()
-            )))),
+            tok => {
+                let value = original.double() * tok.double();
+                inexact(
+                    Operation::Multiply,
+                    vec![Token::Combined(original), tok],
+                    LossReason::NotRepresentable,
+                    value,
+                )
+            }
         }
     }
 
@@ -560,32 +1074,36 @@ impl Set<BasicToken> {
         for (pos, vec_tok) in self.vec.iter().enumerate() {
             match try_add(*vec_tok, tok) {
                 Err(MathError::Overflow) => {
+                    record(
+                        Operation::Add,
+                        vec![Token::Basic(*vec_tok), Token::Basic(tok)],
+                        LossReason::Overflow,
+                    )?;
                     let double = double_check!(self
                         .vec
                         .iter()
                         .fold(tok.double(), |acc, item| acc + item.double()));
                     self.vec.clear();
                     self.vec.push(BasicToken::Double(double));
-                    break;
+                    return Ok(());
                 }
                 Err(MathError::Combine) => {
                     continue;
                 }
                 Ok(BasicToken::Integer(0)) => {
                     self.vec.swap_remove(pos);
-                    break;
+                    return Ok(());
                 }
                 val => {
-                    if discriminant(vec_tok) == discriminant(&val?) {
-                        // If same enum variant.
-                        self.vec.splice(pos..pos + 1, [val?]);
-                        break;
-                    } else {
-                        return self.add(val?);
-                    }
+                    // The sum replaces the term, it is added again in case its type changed.
+                    let val = val?;
+                    self.vec.remove(pos);
+                    return self.add(val);
                 }
             }
         }
+        // Nothing to combine with, e.g. √3 added to 1 + √2.
+        self.vec.push(tok);
         Ok(())
     }
 }
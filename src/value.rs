@@ -0,0 +1,235 @@
+//! The result of evaluating an expression: an exact number, a polynomial when the expression
+//! contains symbols that have no value, an expression such as `cos(x)` that is neither, a
+//! matrix, a list of numbers, or the solutions of an equation.
+
+use crate::linsolve::LinearSolutions;
+use crate::matrix::Matrix;
+use crate::number;
+use crate::polynomial::Polynomial;
+use crate::solve::Solutions;
+use crate::symbolic::{self, Expr};
+use crate::types::{BasicToken, MathError, Token};
+use core::fmt;
+use std::convert::TryFrom;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Number(Token),
+    Polynomial(Polynomial),
+    Expression(Expr),
+    Matrix(Matrix),
+    List(Vec<Token>),
+    Solutions(Solutions),
+    LinearSolutions(LinearSolutions),
+}
+
+impl From<Token> for Value {
+    fn from(token: Token) -> Value {
+        Value::Number(token)
+    }
+}
+
+impl From<Polynomial> for Value {
+    /// Polynomials without symbols become numbers again.
+    fn from(polynomial: Polynomial) -> Value {
+        match polynomial.as_constant() {
+            Some(constant) => Value::Number(constant),
+            None => Value::Polynomial(polynomial),
+        }
+    }
+}
+
+impl Value {
+    /// The number this value holds, symbolic values cannot be used where a number is needed.
+    pub fn number(self) -> Result<Token, MathError> {
+        match self {
+            Value::Number(token) => Ok(token),
+            _ => Err(MathError::NotANumber),
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Token> {
+        match self {
+            Value::Number(token) => Some(token),
+            _ => None,
+        }
+    }
+
+    pub fn polynomial(self) -> Result<Polynomial, MathError> {
+        match self {
+            Value::Number(token) => Ok(Polynomial::constant(token)),
+            Value::Polynomial(polynomial) => Ok(polynomial),
+            _ => Err(MathError::NotANumber),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(token) => fmt::Display::fmt(token, f),
+            Value::Polynomial(polynomial) => write!(f, "{}", polynomial),
+            Value::Expression(expr) => write!(f, "{}", expr),
+            Value::Matrix(matrix) => fmt::Display::fmt(matrix, f),
+            Value::List(list) => {
+                let elements: Vec<String> = list
+                    .iter()
+                    .map(|token| match f.alternate() {
+                        true => format!("{:#}", token),
+                        false => token.to_string(),
+                    })
+                    .collect();
+                write!(f, "{{{}}}", elements.join("; "))
+            }
+            Value::Solutions(solutions) => write!(f, "{}", solutions),
+            Value::LinearSolutions(solutions) => write!(f, "{}", solutions),
+        }
+    }
+}
+
+impl From<Expr> for Value {
+    fn from(expr: Expr) -> Value {
+        match expr {
+            Expr::Number(token) => Value::Number(token),
+            expr => Value::Expression(expr),
+        }
+    }
+}
+
+#[inline]
+fn either_expression(lhs: &Value, rhs: &Value) -> bool {
+    matches!(lhs, Value::Expression(_)) || matches!(rhs, Value::Expression(_))
+}
+
+/// Applies `operation` to both sides as expressions.
+fn expression_operation(
+    lhs: Value,
+    rhs: Value,
+    operation: fn(Expr, Expr) -> Result<Expr, MathError>,
+) -> Result<Value, MathError> {
+    Ok(operation(Expr::try_from(lhs)?, Expr::try_from(rhs)?)?.into())
+}
+
+/// Applies `operation` to every element of a list and a number, or to the elements of two lists
+/// in pairs.
+fn elementwise(
+    lhs: Value,
+    rhs: Value,
+    operation: fn(Token, Token) -> Result<Token, MathError>,
+) -> Result<Value, MathError> {
+    let list = match (lhs, rhs) {
+        (Value::List(lhs), Value::List(rhs)) => {
+            if lhs.len() != rhs.len() {
+                return Err(MathError::LengthMismatch {
+                    lhs: lhs.len() as u32,
+                    rhs: rhs.len() as u32,
+                });
+            }
+            lhs.into_iter()
+                .zip(rhs)
+                .map(|(lhs, rhs)| operation(lhs, rhs))
+                .collect()
+        }
+        (Value::List(list), Value::Number(rhs)) => list
+            .into_iter()
+            .map(|lhs| operation(lhs, rhs.clone()))
+            .collect(),
+        (Value::Number(lhs), Value::List(list)) => list
+            .into_iter()
+            .map(|rhs| operation(lhs.clone(), rhs))
+            .collect(),
+        _ => Err(MathError::NotANumber),
+    };
+    Ok(Value::List(list?))
+}
+
+pub fn add(lhs: Value, rhs: Value) -> Result<Value, MathError> {
+    if either_expression(&lhs, &rhs) {
+        return expression_operation(lhs, rhs, symbolic::sum);
+    }
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok(number::add(lhs, rhs)?.into()),
+        (lhs @ Value::List(_), rhs) | (lhs, rhs @ Value::List(_)) => {
+            elementwise(lhs, rhs, number::add)
+        }
+        (Value::Matrix(lhs), Value::Matrix(rhs)) => Ok(Value::Matrix(lhs.plus(&rhs)?)),
+        (lhs, rhs) => Ok(lhs.polynomial()?.plus(rhs.polynomial()?)?.into()),
+    }
+}
+
+pub fn sub(lhs: Value, rhs: Value) -> Result<Value, MathError> {
+    if either_expression(&lhs, &rhs) {
+        return expression_operation(lhs, rhs, symbolic::difference);
+    }
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok(number::sub(lhs, rhs)?.into()),
+        (lhs @ Value::List(_), rhs) | (lhs, rhs @ Value::List(_)) => {
+            elementwise(lhs, rhs, number::sub)
+        }
+        (Value::Matrix(lhs), Value::Matrix(rhs)) => Ok(Value::Matrix(lhs.minus(&rhs)?)),
+        (lhs, rhs) => Ok(lhs.polynomial()?.minus(rhs.polynomial()?)?.into()),
+    }
+}
+
+pub fn mul(lhs: Value, rhs: Value) -> Result<Value, MathError> {
+    if either_expression(&lhs, &rhs) {
+        return expression_operation(lhs, rhs, symbolic::product);
+    }
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok(number::mul(lhs, rhs)?.into()),
+        (lhs @ Value::List(_), rhs) | (lhs, rhs @ Value::List(_)) => {
+            elementwise(lhs, rhs, number::mul)
+        }
+        (Value::Matrix(lhs), Value::Matrix(rhs)) => Ok(Value::Matrix(lhs.times(&rhs)?)),
+        (Value::Number(factor), Value::Matrix(matrix))
+        | (Value::Matrix(matrix), Value::Number(factor)) => {
+            Ok(Value::Matrix(matrix.scale(&factor)?))
+        }
+        (lhs, rhs) => Ok(lhs.polynomial()?.times(&rhs.polynomial()?)?.into()),
+    }
+}
+
+pub fn div(lhs: Value, rhs: Value) -> Result<Value, MathError> {
+    if either_expression(&lhs, &rhs) {
+        return expression_operation(lhs, rhs, symbolic::quotient);
+    }
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok(number::div(lhs, rhs)?.into()),
+        (lhs @ Value::List(_), rhs) | (lhs, rhs @ Value::List(_)) => {
+            elementwise(lhs, rhs, number::div)
+        }
+        (Value::Polynomial(lhs), Value::Number(rhs)) => Ok(lhs.divide(&rhs)?.into()),
+        (Value::Matrix(lhs), Value::Number(rhs)) => {
+            let factor = number::div(Token::Basic(BasicToken::Integer(1)), rhs)?;
+            Ok(Value::Matrix(lhs.scale(&factor)?))
+        }
+        (_, Value::Polynomial(_)) => Err(MathError::NotPolynomial),
+        _ => Err(MathError::NotANumber),
+    }
+}
+
+pub fn exp(lhs: Value, rhs: Value) -> Result<Value, MathError> {
+    if either_expression(&lhs, &rhs) {
+        return expression_operation(lhs, rhs, symbolic::power);
+    }
+    match (lhs, rhs) {
+        (Value::Number(lhs), Value::Number(rhs)) => Ok(number::exp(lhs, rhs)?.into()),
+        (lhs @ Value::List(_), rhs) | (lhs, rhs @ Value::List(_)) => {
+            elementwise(lhs, rhs, number::exp)
+        }
+        (Value::Polynomial(lhs), Value::Number(Token::Basic(BasicToken::Integer(power))))
+            if power >= 0 =>
+        {
+            let power = u32::try_from(power).map_err(|_| MathError::Overflow)?;
+            Ok(lhs.pow(power)?.into())
+        }
+        (Value::Matrix(lhs), Value::Number(Token::Basic(BasicToken::Integer(power)))) => {
+            Ok(Value::Matrix(lhs.pow(power)?))
+        }
+        (Value::Matrix(_) | Value::Solutions(_) | Value::LinearSolutions(_), _)
+        | (_, Value::Matrix(_) | Value::Solutions(_) | Value::LinearSolutions(_)) => {
+            Err(MathError::NotANumber)
+        }
+        _ => Err(MathError::NotPolynomial),
+    }
+}